@@ -0,0 +1,92 @@
+//! Compression ratio and throughput of [`Compressor`], [`GageCompressor`] and gzip.
+//!
+//! Run with `cargo bench --bench compress`. The ratios are printed once before the timings.
+//! Set `RUST_BPE_BENCH_CORPUS` to a file to compress real text instead of synthetic logs.
+
+use std::io::Write;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rust_bpe::{Compressor, EntropyCoding, GageCompressor};
+
+/// Roughly 1 MB of log lines with repeated fields and varying numbers.
+fn synthetic_logs() -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let levels = ["INFO", "INFO", "INFO", "DEBUG", "WARN", "ERROR"];
+    let events = [
+        "finished job",
+        "started job",
+        "retrying request for",
+        "cache miss on",
+        "connection reset while serving",
+    ];
+    (0..14_000)
+        .map(|i| {
+            format!(
+                "2024-05-{:02} {:02}:{:02}:{:02} {} worker-{} {} {} in {} ms\n",
+                i / 500 + 1,
+                i / 60 % 24,
+                i % 60,
+                next() % 60,
+                levels[(next() % 6) as usize],
+                next() % 16,
+                events[(next() % 5) as usize],
+                next() % 100_000,
+                next() % 5_000
+            )
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn corpus() -> Vec<u8> {
+    match std::env::var("RUST_BPE_BENCH_CORPUS") {
+        Ok(path) => std::fs::read(path).expect("readable corpus"),
+        Err(_) => synthetic_logs(),
+    }
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn bench_compress(c: &mut Criterion) {
+    let data = corpus();
+    let huffman = Compressor::new();
+    let varints = Compressor::new().entropy_coding(EntropyCoding::None);
+    let gage = GageCompressor::new();
+
+    println!("{} bytes", data.len());
+    for (name, len) in [
+        ("bpe", huffman.compress(&data).compressed_len()),
+        ("bpe_varint", varints.compress(&data).compressed_len()),
+        ("gage", gage.compress(&data).compressed_len()),
+        ("gzip_9", gzip(&data).len()),
+    ] {
+        println!(
+            "{name:>10}: {len:>9} bytes, ratio {:.3}",
+            data.len() as f64 / len as f64
+        );
+    }
+
+    let mut group = c.benchmark_group("compress");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("bpe", |b| b.iter(|| huffman.compress(&data)));
+    group.bench_function("bpe_varint", |b| b.iter(|| varints.compress(&data)));
+    group.bench_function("gage", |b| b.iter(|| gage.compress(&data)));
+    group.bench_function("gzip_9", |b| b.iter(|| gzip(&data)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_compress);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! Encoding throughput of the [`EncodeStrategy`] variants.
+//!
+//! Run with `cargo bench --bench encode`. Without a pre-tokenizer every input is a single piece,
+//! which is where the heap strategy pays off.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_bpe::{BpeTrainer, EncodeStrategy, BPE};
+
+fn text(len: usize) -> String {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            char::from(b"etaoin shrdlu"[(state % 13) as usize])
+        })
+        .collect()
+}
+
+fn encode(c: &mut Criterion) {
+    let bpe = BPE::with_trainer(BpeTrainer::new().max_merges(2_000));
+    let vocabulary = bpe.build(&text(200_000));
+
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+    for len in [1_000, 10_000, 30_000] {
+        let input = text(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        for strategy in [EncodeStrategy::Reference, EncodeStrategy::Heap] {
+            let mut bpe = BPE::new();
+            bpe.set_encode_strategy(strategy);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{strategy:?}"), len),
+                &input,
+                |b, input| b.iter(|| bpe.encode(input, &vocabulary)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Training throughput on a synthetic corpus.
+//!
+//! Run with `cargo bench --bench train`, add `--features parallel` to measure the thread pool.
+//! Set `RUST_BPE_BENCH_CORPUS` to a file to train on real text instead.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rust_bpe::{BpeTrainer, RegexPreTokenizer, BPE};
+
+/// Roughly 4 MB of text made of words with a skewed frequency distribution.
+fn synthetic_corpus() -> Vec<String> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let words: Vec<String> = (0..20_000)
+        .map(|_| {
+            let len = 2 + next() % 9;
+            (0..len)
+                .map(|_| char::from(b"etaoinshrdlucmfwypvbgkjqxz"[(next() % 26) as usize]))
+                .collect()
+        })
+        .collect();
+    (0..40_000)
+        .map(|_| {
+            let mut line = String::new();
+            for _ in 0..16 {
+                // Squaring a uniform index favours the first words, like natural text does.
+                let r = (next() % 1_000) as usize;
+                line.push(' ');
+                line.push_str(&words[r * r * words.len() / 1_000_000]);
+            }
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+fn corpus() -> Vec<String> {
+    match std::env::var("RUST_BPE_BENCH_CORPUS") {
+        Ok(path) => std::fs::read_to_string(path)
+            .expect("readable corpus")
+            .split_inclusive('\n')
+            .map(str::to_string)
+            .collect(),
+        Err(_) => synthetic_corpus(),
+    }
+}
+
+fn bench_train(c: &mut Criterion) {
+    let corpus = corpus();
+    let bytes: usize = corpus.iter().map(String::len).sum();
+
+    let mut group = c.benchmark_group("train");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(bytes as u64));
+    for vocab_size in [1_000, 5_000] {
+        let mut bpe = BPE::with_trainer(BpeTrainer::new().vocab_size(vocab_size));
+        bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+        group.bench_function(format!("vocab_size_{vocab_size}"), |b| {
+            b.iter(|| bpe.build_from_iter(&corpus))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_train);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! The GPT-2 byte-to-unicode table.
+//!
+//! Printable bytes map to the character with the same code point, the remaining bytes are
+//! shifted to the code points starting at U+0100. Every byte string thereby becomes a string
+//! of visible characters that can be stored as a JSON key or a whitespace separated line.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+struct Table {
+    chars: [char; 256],
+    bytes: HashMap<char, u8>,
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut chars = ['\0'; 256];
+        let mut shifted = 0;
+        for (byte, ch) in chars.iter_mut().enumerate() {
+            let printable = matches!(byte, 0x21..=0x7e | 0xa1..=0xac | 0xae..=0xff);
+            let code = if printable {
+                byte as u32
+            } else {
+                shifted += 1;
+                255 + shifted
+            };
+            *ch = char::from_u32(code).unwrap();
+        }
+        let bytes = chars.iter().copied().zip(0..=255).collect();
+        Table { chars, bytes }
+    })
+}
+
+/// Spells `bytes` with the byte-to-unicode table.
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let chars = &table().chars;
+    bytes.iter().map(|&b| chars[b as usize]).collect()
+}
+
+/// Inverse of [`encode`], `None` if `text` contains a character outside the table.
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    let bytes = &table().bytes;
+    text.chars().map(|c| bytes.get(&c).copied()).collect()
+}
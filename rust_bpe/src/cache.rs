@@ -0,0 +1,212 @@
+//! A bounded cache of encoded words.
+//!
+//! Natural text repeats the same words over and over, so remembering the ids of the most recently
+//! encoded pieces skips most of the merging.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Pieces longer than this are encoded without the cache. They rarely repeat and would push out
+/// many short words.
+pub(crate) const MAX_WORD_LEN: usize = 256;
+
+/// Marks the missing neighbour of the first and the last entry.
+const NONE: usize = usize::MAX;
+
+/// Usage of the word cache of a [`BPE`](crate::BPE), see
+/// [`set_cache_capacity`](crate::BPE::set_cache_capacity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Pieces whose ids were found in the cache.
+    pub hits: u64,
+    /// Pieces that had to be encoded.
+    pub misses: u64,
+    /// Pieces currently stored.
+    pub len: usize,
+    /// Most pieces stored at once.
+    pub capacity: usize,
+}
+
+/// Identifies the state of a [`Vocabulary`](crate::Vocabulary) that encoded words depend on.
+///
+/// Every change of the merges gets a new revision, so cached ids are never used with a different
+/// vocabulary. Revisions are bookkeeping and all compare equal, so they do not affect the
+/// equality of vocabularies.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Revision(u64);
+
+impl Revision {
+    pub(crate) fn new() -> Revision {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Revision(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl PartialEq for Revision {
+    fn eq(&self, _: &Revision) -> bool {
+        true
+    }
+}
+
+impl Eq for Revision {}
+
+/// Least recently used cache from pieces to their ids, shared by all threads encoding with the
+/// same [`BPE`](crate::BPE).
+#[derive(Debug)]
+pub(crate) struct WordCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl WordCache {
+    pub(crate) fn new(capacity: usize) -> WordCache {
+        WordCache {
+            capacity,
+            entries: Mutex::new(Entries::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The ids of `word` for the vocabulary at `revision`, computed by `encode` if they are not
+    /// cached yet.
+    pub(crate) fn get_or_insert_with(
+        &self,
+        revision: Revision,
+        word: &[u8],
+        encode: impl FnOnce() -> Vec<u32>,
+    ) -> Vec<u32> {
+        if let Some(ids) = self.lock(revision).get(word) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return ids;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Encoding happens outside of the lock, so other threads are not held up.
+        let ids = encode();
+        self.lock(revision).insert(word, &ids, self.capacity);
+        ids
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self
+                .entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .index
+                .len(),
+            capacity: self.capacity,
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Removes all pieces and resets the statistics.
+    pub(crate) fn clear(&self) {
+        *self.entries.lock().unwrap_or_else(|e| e.into_inner()) = Entries::new(self.capacity);
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Locks the entries, dropping them if they belong to another vocabulary.
+    fn lock(&self, revision: Revision) -> std::sync::MutexGuard<'_, Entries> {
+        // The entries stay consistent even if a thread panicked while holding the lock.
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.revision.map(|r| r.0) != Some(revision.0) {
+            *entries = Entries::new(self.capacity);
+            entries.revision = Some(revision);
+        }
+        entries
+    }
+}
+
+/// The cached pieces in a doubly linked list from the most to the least recently used one.
+#[derive(Debug)]
+struct Entries {
+    revision: Option<Revision>,
+    index: HashMap<Vec<u8>, usize>,
+    slots: Vec<Slot>,
+    head: usize,
+    tail: usize,
+}
+
+#[derive(Debug)]
+struct Slot {
+    word: Vec<u8>,
+    ids: Vec<u32>,
+    prev: usize,
+    next: usize,
+}
+
+impl Entries {
+    fn new(capacity: usize) -> Entries {
+        Entries {
+            revision: None,
+            index: HashMap::with_capacity(capacity.min(1 << 16)),
+            slots: Vec::new(),
+            head: NONE,
+            tail: NONE,
+        }
+    }
+
+    fn get(&mut self, word: &[u8]) -> Option<Vec<u32>> {
+        let slot = *self.index.get(word)?;
+        self.unlink(slot);
+        self.push_front(slot);
+        Some(self.slots[slot].ids.clone())
+    }
+
+    fn insert(&mut self, word: &[u8], ids: &[u32], capacity: usize) {
+        if capacity == 0 || self.index.contains_key(word) {
+            return;
+        }
+        let slot = if self.slots.len() < capacity {
+            self.slots.push(Slot {
+                word: word.to_vec(),
+                ids: ids.to_vec(),
+                prev: NONE,
+                next: NONE,
+            });
+            self.slots.len() - 1
+        } else {
+            // Reuse the least recently used slot.
+            let slot = self.tail;
+            self.unlink(slot);
+            self.index.remove(&self.slots[slot].word);
+            self.slots[slot].word = word.to_vec();
+            self.slots[slot].ids = ids.to_vec();
+            slot
+        };
+        self.index.insert(word.to_vec(), slot);
+        self.push_front(slot);
+    }
+
+    fn unlink(&mut self, slot: usize) {
+        let Slot { prev, next, .. } = self.slots[slot];
+        match prev {
+            NONE => self.head = next,
+            prev => self.slots[prev].next = next,
+        }
+        match next {
+            NONE => self.tail = prev,
+            next => self.slots[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, slot: usize) {
+        self.slots[slot].prev = NONE;
+        self.slots[slot].next = self.head;
+        match self.head {
+            NONE => self.tail = slot,
+            head => self.slots[head].prev = slot,
+        }
+        self.head = slot;
+    }
+}
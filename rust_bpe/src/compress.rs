@@ -0,0 +1,391 @@
+//! Compressing arbitrary bytes with merges learned on the input itself.
+//!
+//! A compressed stream is a self-describing container:
+//!
+//! | Field        | Encoding                                                 |
+//! |--------------|----------------------------------------------------------|
+//! | magic        | the 4 bytes `BPEZ`                                       |
+//! | version      | 1 byte, currently `1`                                    |
+//! | flags        | 1 byte, bit 0 set if the tokens are Huffman coded        |
+//! | input length | varint                                                   |
+//! | merge count  | varint                                                   |
+//! | merges       | `left` and `right` token ids as varints, in rank order   |
+//! | symbol count | varint                                                   |
+//! | symbols      | token ids as varints, most frequent first                |
+//! | token count  | varint                                                   |
+//! | tokens       | indexes into the symbols                                 |
+//!
+//! Varints are unsigned LEB128. Ids `0..256` are the single bytes and the merges are replayed
+//! on decompression to rebuild the other tokens, so the ids mean the same thing on both sides.
+//! The token stream refers to tokens through the symbol table, so the most frequent tokens get
+//! the smallest indexes whatever their id.
+//!
+//! Without Huffman coding the indexes are varints, so the 128 most frequent tokens take a
+//! single byte. With Huffman coding, the token count is followed by the longest code length
+//! and the number of codes of every length from 1 up to it, as varints, and then by the
+//! canonical codes of the indexes up to the end of the container. The codes are assigned in
+//! symbol order, so they only depend on the token frequencies.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::encode::encode_bytes_heap;
+use crate::error::{Error, Result};
+use crate::gage;
+use crate::huffman::{self, BitReader, BitWriter};
+use crate::train::BpeTrainer;
+use crate::varint::{self, Reader};
+use crate::vocabulary::Vocabulary;
+
+const MAGIC: &[u8; 4] = b"BPEZ";
+
+const VERSION: u8 = 1;
+
+/// Flag set when the token stream is Huffman coded.
+const HUFFMAN: u8 = 0x01;
+
+/// Longest word the input is split into, and so the longest token. Longer runs, common in
+/// binary data, are cut into several words.
+const MAX_WORD_LEN: usize = 64;
+
+/// Learns merges on the data to compress and stores them with the token stream.
+///
+/// Unlike [`BPE`](crate::BPE), the compressor works on raw bytes: the input does not need to be
+/// UTF-8 and is neither normalized nor split with a pre-tokenizer. Words are runs of letters
+/// and digits, of punctuation or of whitespace, optionally preceded by a single space.
+///
+/// ```rust
+/// use rust_bpe::{decompress, Compressor};
+///
+/// let data = "the cat sat on the mat, the cat ate the rat. ".repeat(20);
+/// let compressed = Compressor::new().compress(data.as_bytes());
+/// assert!(compressed.ratio() > 2.5);
+/// assert_eq!(decompress(compressed.as_bytes()).unwrap(), data.as_bytes());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compressor {
+    vocab_size: usize,
+    min_frequency: u64,
+    entropy_coding: EntropyCoding,
+}
+
+/// How [`Compressor`] stores the token stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EntropyCoding {
+    /// Every token is a varint, readable with a hex dump.
+    None,
+    /// Every token gets a canonical Huffman code built from the token frequencies of the input.
+    /// Frequent tokens take a few bits, which is what makes the output competitive with gzip
+    /// on text.
+    #[default]
+    Huffman,
+}
+
+impl Compressor {
+    /// Default configuration: up to 4096 tokens, pairs must occur at least 4 times, Huffman
+    /// coded tokens.
+    ///
+    /// Every merge costs a few bytes in the merge table, so rare pairs are not worth merging.
+    pub fn new() -> Compressor {
+        Compressor {
+            vocab_size: 4096,
+            min_frequency: 4,
+            entropy_coding: EntropyCoding::Huffman,
+        }
+    }
+
+    /// Maximum number of tokens, counting the 256 byte tokens.
+    pub fn vocab_size(mut self, vocab_size: usize) -> Self {
+        self.vocab_size = vocab_size;
+        self
+    }
+
+    /// Pairs occurring less often than this are never merged.
+    pub fn min_frequency(mut self, min_frequency: u64) -> Self {
+        self.min_frequency = min_frequency;
+        self
+    }
+
+    /// How the token stream is stored.
+    pub fn entropy_coding(mut self, entropy_coding: EntropyCoding) -> Self {
+        self.entropy_coding = entropy_coding;
+        self
+    }
+
+    /// Compresses `data` into a container readable by [`decompress`].
+    pub fn compress(&self, data: &[u8]) -> Compressed {
+        let mut counts: HashMap<&[u8], u64> = HashMap::new();
+        for word in words(data) {
+            *counts.entry(word).or_default() += 1;
+        }
+        let trainer = BpeTrainer::new()
+            .vocab_size(self.vocab_size)
+            .min_frequency(self.min_frequency);
+        let vocabulary = trainer.train(
+            counts
+                .iter()
+                .map(|(word, &count)| (word.iter().map(|&b| u32::from(b)).collect(), count))
+                .collect(),
+        );
+        let segmented: HashMap<&[u8], Vec<u32>> = counts
+            .keys()
+            .map(|&word| (word, encode_bytes_heap(&vocabulary, word)))
+            .collect();
+
+        // Frequent tokens get the small symbols, which take a single byte.
+        let mut frequencies = vec![0u64; vocabulary.len()];
+        for (word, ids) in &segmented {
+            for &id in ids {
+                frequencies[id as usize] += counts[word];
+            }
+        }
+        let mut symbols: Vec<u32> = (0..vocabulary.len() as u32)
+            .filter(|&id| frequencies[id as usize] > 0)
+            .collect();
+        symbols.sort_by_key(|&id| Reverse(frequencies[id as usize]));
+        let mut symbol_of = vec![0; vocabulary.len()];
+        for (symbol, &id) in symbols.iter().enumerate() {
+            symbol_of[id as usize] = symbol;
+        }
+
+        let huffman = self.entropy_coding == EntropyCoding::Huffman
+            && symbols.len() <= 1 << huffman::MAX_CODE_LEN;
+
+        let mut out = Vec::with_capacity(data.len() / 2);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(if huffman { HUFFMAN } else { 0 });
+        varint::write(&mut out, data.len() as u64);
+        varint::write(&mut out, vocabulary.merges().len() as u64);
+        for merge in vocabulary.merges() {
+            varint::write(&mut out, u64::from(merge.left));
+            varint::write(&mut out, u64::from(merge.right));
+        }
+        varint::write(&mut out, symbols.len() as u64);
+        for &id in &symbols {
+            varint::write(&mut out, u64::from(id));
+        }
+
+        let token_count = frequencies.iter().sum::<u64>();
+        varint::write(&mut out, token_count);
+        if huffman {
+            let lengths = huffman::code_lengths(
+                &symbols
+                    .iter()
+                    .map(|&id| frequencies[id as usize])
+                    .collect::<Vec<_>>(),
+            );
+            huffman::write_lengths(&mut out, &lengths);
+            let encoder = huffman::Encoder::new(&lengths);
+            let mut writer = BitWriter::new(&mut out);
+            for word in words(data) {
+                for &id in &segmented[word] {
+                    encoder.write(&mut writer, symbol_of[id as usize] as usize);
+                }
+            }
+            writer.finish();
+        } else {
+            for word in words(data) {
+                for &id in &segmented[word] {
+                    varint::write(&mut out, symbol_of[id as usize] as u64);
+                }
+            }
+        }
+
+        Compressed::new(
+            out,
+            data.len(),
+            vocabulary.merges().len(),
+            token_count as usize,
+        )
+    }
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Compressor::new()
+    }
+}
+
+/// Output of [`Compressor::compress`] and [`GageCompressor::compress`] with statistics about
+/// the compression.
+///
+/// [`GageCompressor::compress`]: crate::GageCompressor::compress
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compressed {
+    data: Vec<u8>,
+    input_len: usize,
+    merges: usize,
+    tokens: usize,
+}
+
+impl Compressed {
+    pub(crate) fn new(data: Vec<u8>, input_len: usize, merges: usize, tokens: usize) -> Compressed {
+        Compressed {
+            data,
+            input_len,
+            merges,
+            tokens,
+        }
+    }
+
+    /// The container bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Takes the container bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Size of the uncompressed input in bytes.
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    /// Size of the container in bytes.
+    pub fn compressed_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Number of merges stored in the container.
+    pub fn merges(&self) -> usize {
+        self.merges
+    }
+
+    /// Number of tokens the input was encoded into.
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Input size divided by container size, above 1 when the data got smaller.
+    pub fn ratio(&self) -> f64 {
+        self.input_len as f64 / self.data.len() as f64
+    }
+}
+
+/// Restores the input of [`Compressor::compress`] or [`GageCompressor::compress`] from its
+/// container, told apart by the magic bytes.
+///
+/// Fails with [`Error::InvalidCompressedData`] if `data` is not a complete container of a
+/// supported version.
+///
+/// [`GageCompressor::compress`]: crate::GageCompressor::compress
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |message: String| Error::InvalidCompressedData(message);
+    let mut reader = Reader::new(data);
+    match reader.bytes(MAGIC.len()) {
+        Ok(magic) if magic == MAGIC => {}
+        Ok(magic) if magic == gage::MAGIC => return gage::decompress(reader),
+        _ => return Err(invalid("missing BPEZ or BPEG header".into())),
+    }
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(invalid(format!("unsupported version {version}")));
+    }
+    let flags = reader.byte()?;
+    if flags & !HUFFMAN != 0 {
+        return Err(invalid(format!("unknown flags {flags:#04x}")));
+    }
+    let len = reader.varint_usize()?;
+
+    let mut vocabulary = Vocabulary::with_bytes();
+    for _ in 0..reader.varint()? {
+        let left = reader.varint_u32()?;
+        let right = reader.varint_u32()?;
+        let merged_len = match (vocabulary.token(left), vocabulary.token(right)) {
+            (Some(left), Some(right)) => left.len() + right.len(),
+            _ => {
+                return Err(invalid(format!(
+                    "merge of unknown tokens {left} and {right}"
+                )))
+            }
+        };
+        // Tokens never grow past the longest word, which also bounds memory on corrupt input.
+        if merged_len > MAX_WORD_LEN {
+            return Err(invalid(format!("merge of {left} and {right} is too long")));
+        }
+        vocabulary.push_merge(left, right);
+    }
+
+    let mut symbols = Vec::new();
+    for _ in 0..reader.varint()? {
+        let id = reader.varint_u32()?;
+        let token = vocabulary
+            .token(id)
+            .ok_or_else(|| invalid(format!("unknown token {id}")))?;
+        symbols.push(token);
+    }
+
+    let mut out = Vec::with_capacity(len.min(data.len().saturating_mul(MAX_WORD_LEN)));
+    let mut push = |token: &[u8]| {
+        out.extend_from_slice(token);
+        if out.len() > len {
+            return Err(invalid(format!("more than the stored {len} bytes")));
+        }
+        Ok(())
+    };
+    let token_count = reader.varint()?;
+    if flags & HUFFMAN != 0 {
+        let decoder = huffman::Decoder::read(&mut reader, symbols.len())?;
+        let mut bits = BitReader::new(reader.rest());
+        for _ in 0..token_count {
+            push(symbols[decoder.decode(&mut bits)?])?;
+        }
+        bits.finish()?;
+    } else {
+        for _ in 0..token_count {
+            let symbol = reader.varint_usize()?;
+            let token = symbols
+                .get(symbol)
+                .ok_or_else(|| invalid(format!("unknown symbol {symbol}")))?;
+            push(token)?;
+        }
+        if !reader.is_empty() {
+            return Err(invalid("trailing bytes after the token stream".into()));
+        }
+    }
+    if out.len() != len {
+        return Err(invalid(format!("{} bytes instead of {len}", out.len())));
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Space,
+    Word,
+    Other,
+}
+
+fn class(byte: u8) -> Class {
+    match byte {
+        b' ' | b'\t' | b'\n' | b'\r' => Class::Space,
+        b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | 0x80.. => Class::Word,
+        _ => Class::Other,
+    }
+}
+
+/// Splits `data` into words of at most [`MAX_WORD_LEN`] bytes.
+fn words(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let limit = rest.len().min(MAX_WORD_LEN);
+        let mut end = 0;
+        if rest[0] == b' ' && limit > 1 && class(rest[1]) != Class::Space {
+            end = 1;
+        }
+        let word_class = class(rest[end]);
+        end += 1;
+        while end < limit && class(rest[end]) == word_class {
+            end += 1;
+        }
+        let (word, tail) = rest.split_at(end);
+        rest = tail;
+        Some(word)
+    })
+}
@@ -0,0 +1,317 @@
+//! Counting the words of training data.
+//!
+//! Training only needs every distinct pre-tokenized piece with its frequency, so the input is
+//! consumed piece by piece and memory is bounded by the number of distinct words, not by the
+//! size of the corpus.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::ops::Range;
+
+use crate::normalizer::Normalizer;
+use crate::pre_tokenizer::{self, PreTokenizer};
+use crate::special::{Segment, SpecialMatcher};
+use crate::stream::{incomplete_len, CHUNK_SIZE, HELD_PIECES};
+
+/// Amount of text collected before it is counted on the thread pool.
+#[cfg(feature = "parallel")]
+const BATCH_SIZE: usize = 8 << 20;
+
+type Counts = HashMap<Vec<u8>, u64>;
+
+/// Splits documents into words like [`BPE::encode`](crate::BPE::encode) would split them.
+struct WordSplitter<'a> {
+    specials: SpecialMatcher<'a>,
+    normalizer: Option<&'a Normalizer>,
+    pre_tokenizer: Option<&'a dyn PreTokenizer>,
+}
+
+impl WordSplitter<'_> {
+    /// Adds the words of the document `text` to `counts`.
+    fn count(&self, counts: &mut Counts, text: &[u8]) {
+        for segment in self.specials.split(text) {
+            let Segment::Text(text) = segment else {
+                continue;
+            };
+            let text = self.normalize(text);
+            count_pieces(
+                counts,
+                &text,
+                &pre_tokenizer::split(self.pre_tokenizer, &text),
+            );
+        }
+    }
+
+    /// Normalizes `raw` text without special tokens, unless there is no normalizer.
+    fn normalize<'t>(&self, raw: &'t [u8]) -> Cow<'t, [u8]> {
+        match self.normalizer {
+            Some(normalizer) => normalizer.normalize_bytes(raw).into(),
+            None => raw.into(),
+        }
+    }
+}
+
+/// Adds `pieces` of `text` to `counts`.
+fn count_pieces(counts: &mut Counts, text: &[u8], pieces: &[Range<usize>]) {
+    for piece in pieces {
+        match counts.get_mut(&text[piece.clone()]) {
+            Some(count) => *count += 1,
+            None => {
+                counts.insert(text[piece.clone()].to_vec(), 1);
+            }
+        }
+    }
+}
+
+/// Input of a reader that has not been counted yet.
+///
+/// Like [`StreamEncoder`](crate::StreamEncoder), only the part of the input that no later bytes
+/// can change is counted: the tail that may begin a special token, an incomplete character and,
+/// with a normalizer, the text after the last ASCII whitespace are kept back, and so are the
+/// last pieces of the built-in pre-tokenizers. Other pre-tokenizers may look arbitrarily far
+/// ahead, so like without a pre-tokenizer the text between two special tokens is only counted
+/// once it is complete.
+struct Pending {
+    /// Pieces kept back from the end of the text, `None` if all of it is kept until it ends.
+    held_pieces: Option<usize>,
+    /// Input that has been read but not split into special tokens and text yet.
+    input: Vec<u8>,
+    /// The length of `input` after it was last searched.
+    held_input: usize,
+    /// Normalized text of the current segment that has not been counted yet.
+    text: Vec<u8>,
+    /// The length of `text` after it was last split.
+    held_text: usize,
+}
+
+impl Pending {
+    fn new(splitter: &WordSplitter<'_>) -> Pending {
+        Pending {
+            held_pieces: splitter
+                .pre_tokenizer
+                .filter(|&pre_tokenizer| pre_tokenizer::is_built_in(pre_tokenizer))
+                .map(|_| HELD_PIECES),
+            input: Vec::new(),
+            held_input: 0,
+            text: Vec::new(),
+            held_text: 0,
+        }
+    }
+
+    /// Reads the next chunk of `reader` and counts what it settles, returning whether the
+    /// input ended.
+    fn read_chunk(
+        &mut self,
+        splitter: &WordSplitter<'_>,
+        counts: &mut Counts,
+        reader: &mut impl Read,
+    ) -> io::Result<bool> {
+        let start = self.input.len();
+        self.input.resize(start + CHUNK_SIZE, 0);
+        let read = loop {
+            match reader.read(&mut self.input[start..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.input.truncate(start);
+                    return Err(err);
+                }
+            }
+        };
+        self.input.truncate(start + read);
+        let end = read == 0;
+        // Input that was held back is only searched again once it has doubled, so a long stretch
+        // without a place to cut costs linear time.
+        if end || self.input.len() >= 2 * self.held_input {
+            self.consume(splitter, counts, end);
+            self.held_input = self.input.len();
+        }
+        Ok(end)
+    }
+
+    /// Splits the settled part of the input into special tokens and text and counts the text
+    /// that is complete, everything if the input `end`ed.
+    fn consume(&mut self, splitter: &WordSplitter<'_>, counts: &mut Counts, end: bool) {
+        let len = self.input.len();
+        // Special tokens starting before `determined` fit into the input, so the matches there
+        // are the matches in the whole input.
+        let determined = match splitter.specials.max_len() {
+            _ if end => len,
+            0 => len,
+            max_len => (len + 1).saturating_sub(max_len),
+        };
+        let matches: Vec<_> = splitter
+            .specials
+            .find_iter(&self.input)
+            .into_iter()
+            .filter(|(range, _)| range.start < determined)
+            .collect();
+        let mut settled = match matches.last() {
+            Some((range, _)) if range.end > determined => range.start,
+            _ => determined,
+        };
+        if !end {
+            let text_start = matches
+                .iter()
+                .map(|(range, _)| range.end)
+                .rfind(|&match_end| match_end <= settled)
+                .unwrap_or(0);
+            let text = &self.input[text_start..settled];
+            settled = match splitter.normalizer {
+                Some(_) => text
+                    .iter()
+                    .rposition(u8::is_ascii_whitespace)
+                    .map_or(text_start, |i| text_start + i),
+                None => settled - incomplete_len(text),
+            };
+        }
+
+        let mut start = 0;
+        for (range, _) in matches
+            .into_iter()
+            .filter(|(range, _)| range.end <= settled)
+        {
+            self.push_text(splitter, start..range.start);
+            self.count_text(splitter, counts, true);
+            start = range.end;
+        }
+        self.push_text(splitter, start..settled);
+        self.count_text(splitter, counts, end);
+        self.input.drain(..settled);
+    }
+
+    /// Normalizes `range` of the input and appends it to the pending text.
+    fn push_text(&mut self, splitter: &WordSplitter<'_>, range: Range<usize>) {
+        let text = splitter.normalize(&self.input[range]);
+        self.text.extend_from_slice(&text);
+    }
+
+    /// Counts the pending pieces that can no longer change, all of them if the text is
+    /// `complete`.
+    fn count_text(&mut self, splitter: &WordSplitter<'_>, counts: &mut Counts, complete: bool) {
+        let held = match self.held_pieces {
+            _ if complete => 0,
+            Some(held) if self.text.len() >= 2 * self.held_text => held,
+            _ => return,
+        };
+        let pieces = pre_tokenizer::split(splitter.pre_tokenizer, &self.text);
+        let settled = pieces.len().saturating_sub(held);
+        count_pieces(counts, &self.text, &pieces[..settled]);
+        let end = pieces[..settled].last().map_or(0, |piece| piece.end);
+        self.text.drain(..end);
+        self.held_text = self.text.len();
+    }
+}
+
+/// Word frequencies of the documents seen so far.
+///
+/// With the `parallel` feature, small documents are collected into batches that are counted on
+/// the rayon thread pool. Frequencies are plain sums, so the result does not depend on how the
+/// documents are distributed over threads.
+pub(crate) struct WordCounter<'a> {
+    splitter: WordSplitter<'a>,
+    counts: Counts,
+    /// Documents waiting to be counted, stored back to back.
+    #[cfg(feature = "parallel")]
+    batch: Vec<u8>,
+    /// End offset of every document in `batch`.
+    #[cfg(feature = "parallel")]
+    batch_ends: Vec<usize>,
+}
+
+impl<'a> WordCounter<'a> {
+    /// Counter leaving out `specials` and splitting the rest with `normalizer` and
+    /// `pre_tokenizer`.
+    pub(crate) fn new(
+        specials: SpecialMatcher<'a>,
+        normalizer: Option<&'a Normalizer>,
+        pre_tokenizer: Option<&'a dyn PreTokenizer>,
+    ) -> WordCounter<'a> {
+        WordCounter {
+            splitter: WordSplitter {
+                specials,
+                normalizer,
+                pre_tokenizer,
+            },
+            counts: HashMap::new(),
+            #[cfg(feature = "parallel")]
+            batch: Vec::new(),
+            #[cfg(feature = "parallel")]
+            batch_ends: Vec::new(),
+        }
+    }
+
+    /// Counts the words of the document `text`.
+    #[cfg(not(feature = "parallel"))]
+    pub(crate) fn add(&mut self, text: &[u8]) {
+        self.splitter.count(&mut self.counts, text);
+    }
+
+    /// Counts the words of the document `text`.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn add(&mut self, text: &[u8]) {
+        if text.len() >= BATCH_SIZE {
+            self.splitter.count(&mut self.counts, text);
+            return;
+        }
+        self.batch.extend_from_slice(text);
+        self.batch_ends.push(self.batch.len());
+        if self.batch.len() >= BATCH_SIZE {
+            self.flush();
+        }
+    }
+
+    /// Documents are counted right away without the `parallel` feature.
+    #[cfg(not(feature = "parallel"))]
+    fn flush(&mut self) {}
+
+    /// Counts the pending batch on the thread pool.
+    #[cfg(feature = "parallel")]
+    fn flush(&mut self) {
+        use rayon::prelude::*;
+
+        let starts = std::iter::once(0).chain(self.batch_ends.iter().copied());
+        let documents: Vec<_> = starts.zip(self.batch_ends.iter().copied()).collect();
+        let (splitter, batch) = (&self.splitter, &self.batch);
+        let counts = documents
+            .into_par_iter()
+            .fold(Counts::new, |mut counts, (start, end)| {
+                splitter.count(&mut counts, &batch[start..end]);
+                counts
+            })
+            .reduce(Counts::new, merge_counts);
+        self.counts = merge_counts(std::mem::take(&mut self.counts), counts);
+        self.batch.clear();
+        self.batch_ends.clear();
+    }
+
+    /// Counts the words of `reader` as one document, reading it in chunks.
+    ///
+    /// The words are those of [`add`](WordCounter::add) with all of the input, see [`Pending`]
+    /// for the input that is kept back until later chunks settle it.
+    pub(crate) fn add_reader(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut pending = Pending::new(&self.splitter);
+        while !pending.read_chunk(&self.splitter, &mut self.counts, &mut reader)? {}
+        Ok(())
+    }
+
+    /// The counted words as byte sequences with their frequency.
+    pub(crate) fn into_words(mut self) -> Vec<(Vec<u32>, u64)> {
+        self.flush();
+        self.counts
+            .into_iter()
+            .map(|(word, count)| (word.iter().map(|&b| u32::from(b)).collect(), count))
+            .collect()
+    }
+}
+
+/// Adds the smaller of two count maps into the larger one.
+#[cfg(feature = "parallel")]
+fn merge_counts(a: Counts, b: Counts) -> Counts {
+    let (mut into, from) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    for (word, count) in from {
+        *into.entry(word).or_default() += count;
+    }
+    into
+}
@@ -0,0 +1,359 @@
+//! Applying learned merges to new input.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::Range;
+
+use crate::cache;
+use crate::encoding::Encoding;
+use crate::pre_tokenizer;
+use crate::special::{Segment, SpecialMatcher};
+use crate::train::merge_word;
+use crate::vocabulary::{MergeMode, Vocabulary};
+use crate::BPE;
+
+/// How [`BPE::encode`](crate::BPE::encode) applies the merges to a pre-tokenized piece.
+///
+/// Every strategy produces exactly the same ids, they only differ in speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeStrategy {
+    /// Rescans the whole piece for the best pair after every merge. This is quadratic in the
+    /// length of a piece, but has no setup cost, which suits the short pieces of a pre-tokenizer.
+    #[default]
+    Reference,
+    /// Keeps the tokens in a linked list and the candidate pairs in a min-heap, so a piece of `n`
+    /// bytes is encoded in `O(n log n)`. This suits long pieces, such as input that is not
+    /// pre-tokenized at all.
+    Heap,
+}
+
+/// Encodes `data` with the settings of `bpe`, emitting the permitted special tokens as they are
+/// and encoding the text between them with [`encode_text`].
+pub(crate) fn encode(bpe: &BPE, vocabulary: &Vocabulary, data: &[u8]) -> Vec<u32> {
+    let mut ids = Vec::new();
+    for segment in SpecialMatcher::for_vocabulary(vocabulary, &bpe.allowed_special).split(data) {
+        match segment {
+            Segment::Text(text) => ids.extend(encode_text(bpe, vocabulary, text)),
+            Segment::Special(id) => ids.push(id),
+        }
+    }
+    ids
+}
+
+/// Encodes `data` following the normalization and pre-tokenization settings of `vocabulary`.
+pub(crate) fn encode_text(bpe: &BPE, vocabulary: &Vocabulary, data: &[u8]) -> Vec<u32> {
+    let normalized;
+    let data = match vocabulary.normalizer() {
+        Some(normalizer) => {
+            normalized = normalizer.normalize_bytes(data);
+            &normalized
+        }
+        None => data,
+    };
+    let prefixed;
+    let data = if vocabulary.add_prefix_space() && !data.is_empty() && data[0] != b' ' {
+        prefixed = [b" ", data].concat();
+        &prefixed
+    } else {
+        data
+    };
+
+    pre_tokenizer::split(vocabulary.pre_tokenizer(), data)
+        .into_iter()
+        .flat_map(|piece| encode_word(bpe, vocabulary, &data[piece]))
+        .collect()
+}
+
+/// Encodes `data` like [`encode`], recording the range of `data` and the piece every token
+/// comes from.
+pub(crate) fn encode_with_offsets(bpe: &BPE, vocabulary: &Vocabulary, data: &[u8]) -> Encoding {
+    let chars = char_indices(data);
+    let char_range = |range: &Range<usize>| match range.is_empty() {
+        true => chars[range.start]..chars[range.start],
+        false => chars[range.start]..chars[range.end - 1] + 1,
+    };
+    let mut encoding = Encoding::default();
+    let mut push = |id: u32, range: Range<usize>, word_id: Option<usize>| {
+        let token = vocabulary.token(id).unwrap_or_default();
+        let token = String::from_utf8_lossy(token).into_owned();
+        let char_range = char_range(&range);
+        encoding.push(id, token, range, char_range, word_id);
+    };
+
+    let specials = SpecialMatcher::for_vocabulary(vocabulary, &bpe.allowed_special).find_iter(data);
+    let text_end = specials
+        .iter()
+        .map(|(range, _)| range.start)
+        .chain([data.len()]);
+    let mut start = 0;
+    let mut words = 0;
+    for (end, special) in text_end.zip(specials.iter().map(Some).chain([None])) {
+        let tokens = encode_text_aligned(bpe, vocabulary, &data[start..end]);
+        let pieces = tokens.last().map_or(0, |&(_, _, word)| word + 1);
+        for (id, range, word) in tokens {
+            push(
+                id,
+                start + range.start..start + range.end,
+                Some(words + word),
+            );
+        }
+        words += pieces;
+        if let Some((range, id)) = special {
+            push(*id, range.clone(), None);
+            start = range.end;
+        }
+    }
+    encoding
+}
+
+/// Encodes `data` like [`encode_text`], returning every token with the range of `data` it was
+/// produced from and the index of its pre-tokenized piece.
+fn encode_text_aligned(
+    bpe: &BPE,
+    vocabulary: &Vocabulary,
+    data: &[u8],
+) -> Vec<(u32, Range<usize>, usize)> {
+    let (mut normalized, mut alignments) = match vocabulary.normalizer() {
+        Some(normalizer) => normalizer.normalize_aligned(data),
+        None => (data.to_vec(), (0..data.len()).map(|i| i..i + 1).collect()),
+    };
+    if vocabulary.add_prefix_space() && !normalized.is_empty() && normalized[0] != b' ' {
+        normalized.insert(0, b' ');
+        alignments.insert(0, 0..0);
+    }
+
+    let mut tokens = Vec::new();
+    for (word, piece) in pre_tokenizer::split(vocabulary.pre_tokenizer(), &normalized)
+        .into_iter()
+        .enumerate()
+    {
+        let mut start = piece.start;
+        for id in encode_word(bpe, vocabulary, &normalized[piece]) {
+            let end = start + vocabulary.token(id).map_or(0, <[u8]>::len);
+            tokens.push((id, alignments[start].start..alignments[end - 1].end, word));
+            start = end;
+        }
+    }
+    tokens
+}
+
+/// The index of the character every byte of `data` belongs to, followed by the number of
+/// characters. An invalid UTF-8 sequence counts as one character.
+fn char_indices(data: &[u8]) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(data.len() + 1);
+    let mut count = 0;
+    for chunk in data.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            indices.extend(std::iter::repeat_n(count, c.len_utf8()));
+            count += 1;
+        }
+        if !chunk.invalid().is_empty() {
+            indices.extend(std::iter::repeat_n(count, chunk.invalid().len()));
+            count += 1;
+        }
+    }
+    indices.push(count);
+    indices
+}
+
+/// Encodes a pre-tokenized piece, looking it up in the word cache of `bpe` first.
+pub(crate) fn encode_word(bpe: &BPE, vocabulary: &Vocabulary, piece: &[u8]) -> Vec<u32> {
+    let encode = || encode_piece(vocabulary, piece, bpe.encode_strategy);
+    match &bpe.cache {
+        Some(cache) if piece.len() <= cache::MAX_WORD_LEN => {
+            cache.get_or_insert_with(vocabulary.revision(), piece, encode)
+        }
+        _ => encode(),
+    }
+}
+
+/// Encodes a single pre-tokenized piece according to the merge mode of `vocabulary`.
+pub(crate) fn encode_piece(
+    vocabulary: &Vocabulary,
+    piece: &[u8],
+    strategy: EncodeStrategy,
+) -> Vec<u32> {
+    match (vocabulary.merge_mode(), strategy) {
+        (MergeMode::Merges, EncodeStrategy::Reference) => encode_bytes(vocabulary, piece),
+        (MergeMode::Merges, EncodeStrategy::Heap) => encode_bytes_heap(vocabulary, piece),
+        (MergeMode::Ranks, strategy) => match vocabulary.token_id(piece) {
+            Some(id) => vec![id],
+            None if strategy == EncodeStrategy::Heap => {
+                encode_ranks_heap(vocabulary, piece, u32::MAX)
+            }
+            None => encode_ranks(vocabulary, piece, u32::MAX),
+        },
+    }
+}
+
+/// Encodes `bytes` by repeatedly applying the lowest-ranked merge present in the sequence.
+///
+/// Applying the merges in rank order reproduces exactly how the training data was segmented,
+/// so the output only depends on the input and the vocabulary.
+pub(crate) fn encode_bytes(vocabulary: &Vocabulary, bytes: &[u8]) -> Vec<u32> {
+    let mut ids: Vec<u32> = bytes.iter().map(|&b| vocabulary.byte_id(b)).collect();
+    loop {
+        let best = ids
+            .windows(2)
+            .filter_map(|pair| vocabulary.merge_rank(pair[0], pair[1]))
+            .min();
+        match best {
+            Some(rank) => {
+                let merge = vocabulary.merges()[rank];
+                merge_word(&mut ids, merge.left, merge.right, merge.id);
+            }
+            None => return ids,
+        }
+    }
+}
+
+/// Encodes `bytes` by repeatedly merging the leftmost adjacent pair that forms the token with
+/// the lowest id. Only tokens with an id below `max_rank` are formed.
+pub(crate) fn encode_ranks(vocabulary: &Vocabulary, bytes: &[u8], max_rank: u32) -> Vec<u32> {
+    // Token `i` spans `bytes[bounds[i]..bounds[i + 1]]`.
+    let mut bounds: Vec<usize> = (0..=bytes.len()).collect();
+    loop {
+        let best = (0..bounds.len().saturating_sub(2))
+            .filter_map(|i| {
+                let rank = vocabulary.token_id(&bytes[bounds[i]..bounds[i + 2]])?;
+                (rank < max_rank).then_some((rank, i))
+            })
+            .min();
+        match best {
+            Some((_, i)) => {
+                bounds.remove(i + 1);
+            }
+            None => break,
+        }
+    }
+    bounds
+        .windows(2)
+        .map(|token| vocabulary.token_id(&bytes[token[0]..token[1]]).unwrap())
+        .collect()
+}
+
+/// Marks the missing neighbour of the first and the last symbol.
+const NONE: usize = usize::MAX;
+
+/// The tokens of a piece as a doubly linked list. Symbol `i` starts at byte `i` of the piece, so
+/// the byte positions order the symbols.
+struct Symbols {
+    ids: Vec<u32>,
+    prev: Vec<usize>,
+    next: Vec<usize>,
+}
+
+impl Symbols {
+    fn new(ids: Vec<u32>) -> Symbols {
+        let len = ids.len();
+        Symbols {
+            ids,
+            prev: (0..len).map(|i| i.wrapping_sub(1)).collect(),
+            next: (1..=len).map(|i| if i == len { NONE } else { i }).collect(),
+        }
+    }
+
+    /// Whether symbol `i` has not been merged into its left neighbour.
+    fn is_alive(&self, i: usize) -> bool {
+        i == 0 || self.prev[i] != NONE
+    }
+
+    /// Merges symbol `i` with its right neighbour into the token `id`.
+    fn merge(&mut self, i: usize, id: u32) {
+        let right = self.next[i];
+        let after = self.next[right];
+        self.ids[i] = id;
+        self.next[i] = after;
+        if after != NONE {
+            self.prev[after] = i;
+        }
+        self.prev[right] = NONE;
+        self.next[right] = NONE;
+    }
+
+    /// Start positions of the remaining symbols, in order.
+    fn starts(&self) -> impl Iterator<Item = usize> + '_ {
+        let first = (!self.ids.is_empty()).then_some(0);
+        std::iter::successors(first, |&i| Some(self.next[i]).filter(|&next| next != NONE))
+    }
+}
+
+/// Same result as [`encode_bytes`], merging from a heap of `(rank, position)` candidates.
+///
+/// All candidates of the lowest rank are taken from the heap together and merged left to right,
+/// which is exactly what one round of [`encode_bytes`] does.
+pub(crate) fn encode_bytes_heap(vocabulary: &Vocabulary, bytes: &[u8]) -> Vec<u32> {
+    let mut symbols = Symbols::new(bytes.iter().map(|&b| vocabulary.byte_id(b)).collect());
+    let rank_at = |symbols: &Symbols, i: usize| {
+        let next = symbols.next[i];
+        (next != NONE)
+            .then(|| vocabulary.merge_rank(symbols.ids[i], symbols.ids[next]))
+            .flatten()
+    };
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = (0..bytes.len())
+        .filter_map(|i| Some(Reverse((rank_at(&symbols, i)?, i))))
+        .collect();
+
+    let mut batch = Vec::new();
+    while let Some(&Reverse((rank, _))) = heap.peek() {
+        while let Some(&Reverse((next_rank, i))) = heap.peek() {
+            if next_rank != rank {
+                break;
+            }
+            heap.pop();
+            batch.push(i);
+        }
+        let id = vocabulary.merges()[rank].id;
+        for i in batch.drain(..) {
+            if !symbols.is_alive(i) || rank_at(&symbols, i) != Some(rank) {
+                continue;
+            }
+            symbols.merge(i, id);
+            let prev = symbols.prev[i];
+            for i in [prev, i] {
+                if let Some(rank) = (i != NONE).then(|| rank_at(&symbols, i)).flatten() {
+                    heap.push(Reverse((rank, i)));
+                }
+            }
+        }
+    }
+    symbols.starts().map(|i| symbols.ids[i]).collect()
+}
+
+/// Same result as [`encode_ranks`], merging from a heap of `(rank, position)` candidates.
+pub(crate) fn encode_ranks_heap(vocabulary: &Vocabulary, bytes: &[u8], max_rank: u32) -> Vec<u32> {
+    // Only the positions matter, the ids are looked up from the final spans.
+    let mut symbols = Symbols::new(vec![0; bytes.len()]);
+    let end = |symbols: &Symbols, i: usize| match symbols.next[i] {
+        NONE => bytes.len(),
+        next => next,
+    };
+    let rank_at = |symbols: &Symbols, i: usize| {
+        let next = symbols.next[i];
+        if next == NONE {
+            return None;
+        }
+        let rank = vocabulary.token_id(&bytes[i..end(symbols, next)])?;
+        (rank < max_rank).then_some(rank)
+    };
+    let mut heap: BinaryHeap<Reverse<(u32, usize)>> = (0..bytes.len())
+        .filter_map(|i| Some(Reverse((rank_at(&symbols, i)?, i))))
+        .collect();
+
+    while let Some(Reverse((rank, i))) = heap.pop() {
+        if !symbols.is_alive(i) || rank_at(&symbols, i) != Some(rank) {
+            continue;
+        }
+        symbols.merge(i, rank);
+        let prev = symbols.prev[i];
+        for i in [prev, i] {
+            if let Some(rank) = (i != NONE).then(|| rank_at(&symbols, i)).flatten() {
+                heap.push(Reverse((rank, i)));
+            }
+        }
+    }
+    symbols
+        .starts()
+        .map(|i| vocabulary.token_id(&bytes[i..end(&symbols, i)]).unwrap())
+        .collect()
+}
@@ -0,0 +1,219 @@
+//! Encodings that keep track of where every token comes from.
+
+use std::ops::Range;
+
+use crate::truncation::Side;
+
+/// The tokens of an input together with their position in it, as returned by
+/// [`BPE::encode_with_offsets`](crate::BPE::encode_with_offsets).
+///
+/// All vectors have one entry per token. Offsets always refer to the input as it was passed in,
+/// before any normalization.
+///
+/// ```rust
+/// use rust_bpe::{Normalizer, Whitespace, BPE};
+///
+/// let mut bpe = BPE::new();
+/// bpe.set_normalizer(Normalizer::Lowercase);
+/// bpe.set_pre_tokenizer(Whitespace);
+/// let vocabulary = bpe.build("hello hello");
+///
+/// let encoding = bpe.encode_with_offsets("Élan HELLO", &vocabulary);
+/// let last = encoding.len() - 1;
+/// assert_eq!(encoding.tokens()[last], "hello");
+/// assert_eq!(encoding.offsets()[last], 6..11);
+/// assert_eq!(encoding.char_offsets()[last], 5..10);
+/// assert_eq!(encoding.word_ids()[last], Some(2));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Encoding {
+    ids: Vec<u32>,
+    tokens: Vec<String>,
+    offsets: Vec<Range<usize>>,
+    char_offsets: Vec<Range<usize>>,
+    word_ids: Vec<Option<usize>>,
+    type_ids: Vec<u32>,
+    attention_mask: Vec<u32>,
+    overflowing: Vec<Encoding>,
+}
+
+impl Encoding {
+    pub(crate) fn push(
+        &mut self,
+        id: u32,
+        token: String,
+        offsets: Range<usize>,
+        char_offsets: Range<usize>,
+        word_id: Option<usize>,
+    ) {
+        self.ids.push(id);
+        self.tokens.push(token);
+        self.offsets.push(offsets);
+        self.char_offsets.push(char_offsets);
+        self.word_ids.push(word_id);
+        self.type_ids.push(0);
+        self.attention_mask.push(1);
+    }
+
+    /// The tokens in `range`, without overflowing parts.
+    fn slice(&self, range: Range<usize>) -> Encoding {
+        Encoding {
+            ids: self.ids[range.clone()].to_vec(),
+            tokens: self.tokens[range.clone()].to_vec(),
+            offsets: self.offsets[range.clone()].to_vec(),
+            char_offsets: self.char_offsets[range.clone()].to_vec(),
+            word_ids: self.word_ids[range.clone()].to_vec(),
+            type_ids: self.type_ids[range.clone()].to_vec(),
+            attention_mask: self.attention_mask[range].to_vec(),
+            overflowing: Vec::new(),
+        }
+    }
+
+    /// Keeps at most `max_length` tokens, cut from `side`. The cut tokens are moved to
+    /// [`overflowing`](Encoding::overflowing) in windows of `max_length` tokens, neighbouring
+    /// windows sharing `stride` tokens.
+    pub(crate) fn truncate(&mut self, max_length: usize, stride: usize, side: Side) {
+        let len = self.len();
+        if len <= max_length {
+            return;
+        }
+        if max_length == 0 {
+            *self = self.slice(0..0);
+            return;
+        }
+        let step = max_length - stride.min(max_length - 1);
+        let mut windows = Vec::new();
+        let mut done = false;
+        while !done {
+            let skipped = windows.len() * step;
+            let window = match side {
+                Side::Right => skipped..(skipped + max_length).min(len),
+                Side::Left => (len - skipped).saturating_sub(max_length)..len - skipped,
+            };
+            done = match side {
+                Side::Right => window.end == len,
+                Side::Left => window.start == 0,
+            };
+            windows.push(window);
+        }
+        let mut parts: Vec<_> = windows
+            .into_iter()
+            .map(|window| self.slice(window))
+            .collect();
+        let overflowing = parts.split_off(1);
+        *self = parts.swap_remove(0);
+        self.overflowing = overflowing;
+    }
+
+    /// A special token added around the input, such as a separator.
+    pub(crate) fn push_special(&mut self, id: u32, token: String, type_id: u32) {
+        self.push(id, token, 0..0, 0..0, None);
+        *self.type_ids.last_mut().unwrap() = type_id;
+    }
+
+    /// Appends the tokens of `other` as part of the sequence `type_id`, without its overflowing
+    /// parts.
+    pub(crate) fn extend(&mut self, other: &Encoding, type_id: u32) {
+        self.ids.extend_from_slice(&other.ids);
+        self.tokens.extend_from_slice(&other.tokens);
+        self.offsets.extend_from_slice(&other.offsets);
+        self.char_offsets.extend_from_slice(&other.char_offsets);
+        self.word_ids.extend_from_slice(&other.word_ids);
+        self.type_ids
+            .extend(std::iter::repeat_n(type_id, other.len()));
+        self.attention_mask.extend_from_slice(&other.attention_mask);
+    }
+
+    pub(crate) fn take_overflowing(&mut self) -> Vec<Encoding> {
+        std::mem::take(&mut self.overflowing)
+    }
+
+    pub(crate) fn set_overflowing(&mut self, overflowing: Vec<Encoding>) {
+        self.overflowing = overflowing;
+    }
+
+    /// Adds padding tokens on `side` until there are `length` tokens. The padding tokens have an
+    /// attention mask of 0, no word and empty offsets.
+    pub(crate) fn pad(&mut self, length: usize, pad_id: u32, pad_token: &str, side: Side) {
+        for overflowing in &mut self.overflowing {
+            overflowing.pad(length, pad_id, pad_token, side);
+        }
+        let missing = length.saturating_sub(self.len());
+        if missing == 0 {
+            return;
+        }
+        let at = match side {
+            Side::Left => 0,
+            Side::Right => self.len(),
+        };
+        fn fill<T: Clone>(values: &mut Vec<T>, at: usize, value: T, missing: usize) {
+            values.splice(at..at, std::iter::repeat_n(value, missing));
+        }
+        fill(&mut self.ids, at, pad_id, missing);
+        fill(&mut self.tokens, at, pad_token.to_string(), missing);
+        fill(&mut self.offsets, at, 0..0, missing);
+        fill(&mut self.char_offsets, at, 0..0, missing);
+        fill(&mut self.word_ids, at, None, missing);
+        fill(&mut self.type_ids, at, 0, missing);
+        fill(&mut self.attention_mask, at, 0, missing);
+    }
+
+    /// The token ids, the same as [`BPE::encode`](crate::BPE::encode) returns.
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// The bytes of every token as text, with invalid UTF-8 replaced by `U+FFFD`.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// The byte range of the input every token was produced from.
+    ///
+    /// Tokens produced from the same normalized characters share their range, and a space
+    /// added by [`add_prefix_space`](crate::Vocabulary::add_prefix_space) has an empty range.
+    pub fn offsets(&self) -> &[Range<usize>] {
+        &self.offsets
+    }
+
+    /// The same ranges as [`offsets`](Encoding::offsets), counted in characters. A token that
+    /// ends within a character includes all of it, and an invalid UTF-8 sequence counts as one
+    /// character like in [`String::from_utf8_lossy`].
+    pub fn char_offsets(&self) -> &[Range<usize>] {
+        &self.char_offsets
+    }
+
+    /// The index of the pre-tokenized piece every token belongs to, counted over the whole input.
+    /// Special tokens are not part of a piece and have `None`. The word ids of the second input of
+    /// a pair start at `0` again.
+    pub fn word_ids(&self) -> &[Option<usize>] {
+        &self.word_ids
+    }
+
+    /// The sequence every token belongs to, as assigned by the
+    /// [`PostProcessor`](crate::PostProcessor). Without one, the tokens of the second input of
+    /// a pair have type `1` and all others type `0`.
+    pub fn type_ids(&self) -> &[u32] {
+        &self.type_ids
+    }
+
+    /// `1` for every token of the input and `0` for padding.
+    pub fn attention_mask(&self) -> &[u32] {
+        &self.attention_mask
+    }
+
+    /// The tokens cut off by truncation, as further encodings of at most the maximum length.
+    pub fn overflowing(&self) -> &[Encoding] {
+        &self.overflowing
+    }
+
+    /// Number of tokens.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether there are no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
@@ -0,0 +1,89 @@
+//! Error type shared by the fallible operations of the crate.
+
+use std::fmt;
+use std::io;
+use std::string::FromUtf8Error;
+
+/// Errors returned by this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A token id that is not part of the vocabulary.
+    UnknownToken(u32),
+    /// Decoded bytes are not valid UTF-8.
+    InvalidUtf8(FromUtf8Error),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// A file is not valid JSON.
+    Json(serde_json::Error),
+    /// A vocabulary file was written with an unknown schema version.
+    UnsupportedVersion(u64),
+    /// A vocabulary file is well-formed but its contents are inconsistent.
+    InvalidVocabulary(String),
+    /// A vocabulary file uses a feature this crate does not implement.
+    Unsupported(String),
+    /// A pre-tokenization pattern is not a valid regular expression.
+    InvalidPattern(String),
+    /// A post-processing template is malformed or refers to unknown special tokens.
+    InvalidTemplate(String),
+    /// Compressed data is truncated, corrupt or not produced by this crate.
+    InvalidCompressedData(String),
+}
+
+/// Result type of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownToken(id) => write!(f, "unknown token id {id}"),
+            Error::InvalidUtf8(err) => write!(f, "decoded bytes are not valid UTF-8: {err}"),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Json(err) => write!(f, "invalid JSON: {err}"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported vocabulary format version {version}")
+            }
+            Error::InvalidVocabulary(message) => write!(f, "invalid vocabulary: {message}"),
+            Error::Unsupported(message) => write!(f, "unsupported: {message}"),
+            Error::InvalidPattern(message) => write!(f, "invalid pattern {message}"),
+            Error::InvalidTemplate(message) => write!(f, "invalid template: {message}"),
+            Error::InvalidCompressedData(message) => {
+                write!(f, "invalid compressed data: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidUtf8(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            Error::UnknownToken(_)
+            | Error::UnsupportedVersion(_)
+            | Error::InvalidVocabulary(_)
+            | Error::Unsupported(_)
+            | Error::InvalidPattern(_)
+            | Error::InvalidTemplate(_)
+            | Error::InvalidCompressedData(_) => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Self {
+        Error::InvalidUtf8(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
@@ -0,0 +1,316 @@
+//! The original byte pair encoding of Philip Gage, "A New Algorithm for Data Compression",
+//! C Users Journal, 1994.
+//!
+//! The input is cut into blocks. Within a block, the most frequent pair of adjacent bytes is
+//! replaced by a byte value that does not occur in the block, until no pair is frequent enough
+//! or no byte value is left. The compressed file is:
+//!
+//! | Field        | Encoding                                  |
+//! |--------------|-------------------------------------------|
+//! | magic        | the 4 bytes `BPEG`                        |
+//! | version      | 1 byte, currently `1`                     |
+//! | input length | varint                                    |
+//! | blocks       | until the end of the file                 |
+//!
+//! and every block is its pair table, the packed length as a varint and the packed bytes.
+//!
+//! The pair table uses Gage's layout. It walks the byte values in order, with a count byte in
+//! front of every run: a count `n > 127` skips `n - 127` values that stand for themselves, a
+//! count `n <= 127` is followed by `n + 1` entries. An entry is the left byte of the pair, or
+//! the value itself if it stands for itself, followed by the right byte for pairs.
+
+use crate::compress::Compressed;
+use crate::error::{Error, Result};
+use crate::varint::{self, Reader};
+
+pub(crate) const MAGIC: &[u8; 4] = b"BPEG";
+
+const VERSION: u8 = 1;
+
+/// A block ends early once it contains this many distinct byte values, so that some values are
+/// left to stand for pairs.
+const MAX_CHARS: usize = 200;
+
+/// Expanding a valid table never needs a deeper stack, each of the 256 values can occur at
+/// most once on the path from a byte to the literal it expands to.
+const MAX_STACK: usize = 512;
+
+/// Compresses data block by block, replacing frequent pairs with unused byte values.
+///
+/// This is the classic algorithm: every block carries its own pair table and bytes stay bytes,
+/// so no vocabulary is learned across the input. [`decompress`](crate::decompress) restores the
+/// data like it does for [`Compressor`](crate::Compressor).
+///
+/// ```rust
+/// use rust_bpe::{decompress, GageCompressor};
+///
+/// let data = "ERROR disk full\nERROR disk full\nWARN disk almost full\n".repeat(10);
+/// let compressed = GageCompressor::new().compress(data.as_bytes());
+/// assert!(compressed.ratio() > 3.0);
+/// assert_eq!(decompress(compressed.as_bytes()).unwrap(), data.as_bytes());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GageCompressor {
+    block_size: usize,
+    min_frequency: u32,
+}
+
+impl GageCompressor {
+    /// Gage's configuration: blocks of up to 5000 bytes, pairs must occur at least 3 times.
+    pub fn new() -> GageCompressor {
+        GageCompressor {
+            block_size: 5000,
+            min_frequency: 3,
+        }
+    }
+
+    /// Maximum number of input bytes in a block. Larger blocks find more repeated pairs but
+    /// have fewer unused byte values to replace them with. A size of 0 is treated as 1.
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Pairs occurring less often than this in a block are not replaced.
+    pub fn min_frequency(mut self, min_frequency: u32) -> Self {
+        self.min_frequency = min_frequency;
+        self
+    }
+
+    /// Compresses `data` into a container readable by [`decompress`](crate::decompress).
+    ///
+    /// [`Compressed::merges`] counts the pairs of all blocks and [`Compressed::tokens`] the
+    /// packed bytes.
+    pub fn compress(&self, data: &[u8]) -> Compressed {
+        let mut out = Vec::with_capacity(data.len() / 2);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        varint::write(&mut out, data.len() as u64);
+
+        let mut pairs = 0;
+        let mut tokens = 0;
+        let mut rest = data;
+        while !rest.is_empty() {
+            let len = self.block_len(rest);
+            let (block, tail) = rest.split_at(len);
+            rest = tail;
+
+            let block = Block::pack(block, self.min_frequency.max(1));
+            write_pair_table(&mut out, &block.left, &block.right);
+            varint::write(&mut out, block.data.len() as u64);
+            out.extend_from_slice(&block.data);
+            pairs += block.pairs;
+            tokens += block.data.len();
+        }
+
+        Compressed::new(out, data.len(), pairs, tokens)
+    }
+
+    /// Length of the block at the start of `data`.
+    fn block_len(&self, data: &[u8]) -> usize {
+        let mut seen = [false; 256];
+        let mut distinct = 0;
+        for (i, &byte) in data.iter().take(self.block_size).enumerate() {
+            if !seen[byte as usize] {
+                if distinct == MAX_CHARS {
+                    return i;
+                }
+                seen[byte as usize] = true;
+                distinct += 1;
+            }
+        }
+        data.len().min(self.block_size)
+    }
+}
+
+impl Default for GageCompressor {
+    fn default() -> Self {
+        GageCompressor::new()
+    }
+}
+
+/// A packed block with its pair table. A value `c` stands for itself if `left[c] == c`, and
+/// for the pair `left[c]`, `right[c]` otherwise.
+struct Block {
+    data: Vec<u8>,
+    left: [u8; 256],
+    right: [u8; 256],
+    pairs: usize,
+}
+
+impl Block {
+    fn pack(block: &[u8], min_frequency: u32) -> Block {
+        let mut data = block.to_vec();
+        let mut left: [u8; 256] = std::array::from_fn(|c| c as u8);
+        let mut right = [0u8; 256];
+        let mut used = [false; 256];
+        for &byte in &data {
+            used[byte as usize] = true;
+        }
+
+        let mut pairs = 0;
+        // Only the slots of pairs present in the block are touched and reset in a round, a block
+        // holds far fewer than the 65,536 possible pairs.
+        let mut counts = vec![0u32; 1 << 16];
+        let mut present = Vec::new();
+        while let Some(code) = used.iter().position(|&used| !used) {
+            for pair in data.windows(2) {
+                let pair = usize::from(pair[0]) << 8 | usize::from(pair[1]);
+                if counts[pair] == 0 {
+                    present.push(pair);
+                }
+                counts[pair] += 1;
+            }
+            // The first of the most frequent pairs, so the output does not depend on chance.
+            let mut best = 0;
+            let mut count = 0;
+            for pair in present.drain(..) {
+                if counts[pair] > count || (counts[pair] == count && pair < best) {
+                    best = pair;
+                    count = counts[pair];
+                }
+                counts[pair] = 0;
+            }
+            if count < min_frequency {
+                break;
+            }
+            let (a, b) = ((best >> 8) as u8, best as u8);
+            replace_pair(&mut data, a, b, code as u8);
+            left[code] = a;
+            right[code] = b;
+            used[code] = true;
+            pairs += 1;
+        }
+
+        Block {
+            data,
+            left,
+            right,
+            pairs,
+        }
+    }
+}
+
+/// Replaces every non-overlapping occurrence of `a`, `b` in `data` with `code`, left to right.
+fn replace_pair(data: &mut Vec<u8>, a: u8, b: u8, code: u8) {
+    let mut read = 0;
+    let mut write = 0;
+    while read < data.len() {
+        if read + 1 < data.len() && data[read] == a && data[read + 1] == b {
+            data[write] = code;
+            read += 2;
+        } else {
+            data[write] = data[read];
+            read += 1;
+        }
+        write += 1;
+    }
+    data.truncate(write);
+}
+
+/// Writes the table in Gage's layout. Runs of pairs may include a single value standing for
+/// itself when that is shorter than starting a new run.
+fn write_pair_table(out: &mut Vec<u8>, left: &[u8; 256], right: &[u8; 256]) {
+    let is_pair = |c: usize| usize::from(left[c]) != c;
+    let mut c = 0;
+    while c < 256 {
+        let mut len = 0;
+        if !is_pair(c) {
+            let mut run = 1;
+            c += 1;
+            while run < 127 && c < 256 && !is_pair(c) {
+                run += 1;
+                c += 1;
+            }
+            out.push(run + 127);
+            if c == 256 {
+                break;
+            }
+        } else {
+            c += 1;
+            while (len < 127 && c < 256 && is_pair(c)) || (len < 125 && c < 254 && is_pair(c + 1)) {
+                len += 1;
+                c += 1;
+            }
+            out.push(len as u8);
+            c -= len + 1;
+        }
+        for _ in 0..=len {
+            out.push(left[c]);
+            if is_pair(c) {
+                out.push(right[c]);
+            }
+            c += 1;
+        }
+    }
+}
+
+fn read_pair_table(reader: &mut Reader<'_>) -> Result<([u8; 256], [u8; 256])> {
+    let mut left: [u8; 256] = std::array::from_fn(|c| c as u8);
+    let mut right = [0u8; 256];
+    let mut c = 0;
+    while c < 256 {
+        let mut count = usize::from(reader.byte()?);
+        if count > 127 {
+            c += count - 127;
+            count = 0;
+            if c == 256 {
+                break;
+            }
+        }
+        if c + count >= 256 {
+            return Err(invalid("pair table runs past byte 255"));
+        }
+        for _ in 0..=count {
+            left[c] = reader.byte()?;
+            if usize::from(left[c]) != c {
+                right[c] = reader.byte()?;
+            }
+            c += 1;
+        }
+    }
+    Ok((left, right))
+}
+
+/// Restores a container written by [`GageCompressor::compress`], `reader` being positioned
+/// after the magic bytes.
+pub(crate) fn decompress(mut reader: Reader<'_>) -> Result<Vec<u8>> {
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(invalid(format!("unsupported version {version}")));
+    }
+    let len = reader.varint_usize()?;
+
+    // The stored length is not trusted for the allocation, packed bytes rarely expand to more
+    // than a few bytes each and the output grows past that as it is decoded.
+    let mut out = Vec::with_capacity(len.min(reader.len().saturating_mul(4)));
+    let mut stack = Vec::with_capacity(MAX_STACK);
+    while !reader.is_empty() {
+        let (left, right) = read_pair_table(&mut reader)?;
+        let packed_len = reader.varint_usize()?;
+        for &byte in reader.bytes(packed_len)? {
+            stack.push(byte);
+            while let Some(c) = stack.pop() {
+                if usize::from(left[c as usize]) == usize::from(c) {
+                    out.push(c);
+                    if out.len() > len {
+                        return Err(invalid(format!("more than the stored {len} bytes")));
+                    }
+                } else if stack.len() + 2 > MAX_STACK {
+                    return Err(invalid("pair table refers to itself"));
+                } else {
+                    stack.push(right[c as usize]);
+                    stack.push(left[c as usize]);
+                }
+            }
+        }
+    }
+    if out.len() != len {
+        return Err(invalid(format!("{} bytes instead of {len}", out.len())));
+    }
+    Ok(out)
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidCompressedData(message.into())
+}
@@ -0,0 +1,73 @@
+//! Import and export of the GPT-2 `vocab.json` and `merges.txt` pair.
+//!
+//! `vocab.json` maps every token, spelled with the byte-to-unicode table, to its id.
+//! `merges.txt` lists one merge per line as the two tokens separated by a space, in rank order,
+//! optionally preceded by a `#version` line. The format has no notion of special tokens, only
+//! `<|endoftext|>` is recognized by name.
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::hf::{byte_level_merges, byte_level_vocab, collect_tokens, parse_merge, parse_vocab};
+use crate::json::{invalid, read_json, token_id};
+use crate::pre_tokenizer::RegexPreTokenizer;
+use crate::vocabulary::Vocabulary;
+
+/// The end-of-text marker of GPT-2, loaded as a special token.
+const END_OF_TEXT: &str = "<|endoftext|>";
+
+impl Vocabulary {
+    /// Reads a GPT-2 style `vocab.json` and `merges.txt`.
+    ///
+    /// The vocabulary splits input with the GPT-2 regex, so encoding reproduces the token ids of
+    /// the original tokenizer.
+    pub fn from_gpt2_files(
+        vocab_path: impl AsRef<Path>,
+        merges_path: impl AsRef<Path>,
+    ) -> Result<Vocabulary> {
+        let vocab = read_json(vocab_path.as_ref())?;
+        let vocab = vocab
+            .as_object()
+            .ok_or_else(|| invalid("vocab.json must contain an object"))?;
+        let tokens = collect_tokens(parse_vocab(vocab, vocab.len())?)?;
+
+        let merges = fs::read_to_string(merges_path)?
+            .lines()
+            .filter(|line| !line.starts_with("#version") && !line.trim().is_empty())
+            .map(|line| match line.split_once(' ') {
+                Some((left, right)) => parse_merge(vocab, left, right),
+                None => Err(invalid(format!("invalid merge line {line:?}"))),
+            })
+            .collect::<Result<_>>()?;
+
+        let special_tokens = vocab.get(END_OF_TEXT).map(token_id).transpose()?;
+        let special_tokens = special_tokens.into_iter().collect();
+        let mut vocabulary = Vocabulary::from_parts(tokens, merges, special_tokens)?;
+        vocabulary.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+        Ok(vocabulary)
+    }
+
+    /// Writes the vocabulary as a GPT-2 style `vocab.json` and `merges.txt`.
+    ///
+    /// Special tokens are written to `vocab.json` like any other token.
+    pub fn save_gpt2_files(
+        &self,
+        vocab_path: impl AsRef<Path>,
+        merges_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let vocab = Value::Object(byte_level_vocab(self, true));
+        fs::write(vocab_path, serde_json::to_string(&vocab)?)?;
+
+        let mut merges = BufWriter::new(fs::File::create(merges_path)?);
+        writeln!(merges, "#version: 0.2")?;
+        for (left, right) in byte_level_merges(self) {
+            writeln!(merges, "{left} {right}")?;
+        }
+        merges.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+//! Import and export of Hugging Face `tokenizer.json` files.
+//!
+//! Only byte-level BPE models are supported, which covers GPT-2 and its descendants. Vocabulary
+//! entries and merges are spelled with the byte-to-unicode table, `added_tokens` marked as
+//! `special` become special tokens and the others [added tokens](Vocabulary::added_tokens), all
+//! of them matched on the input before it is normalized. The `ByteLevel` pre-tokenizer maps to the GPT-2
+//! [`RegexPreTokenizer`], a `Split` on a regex followed by `ByteLevel` without regex, as written
+//! for tiktoken-style models, maps to a [`RegexPreTokenizer`] with that pattern. Normalizers are
+//! supported as far as [`Normalizer`] covers them. A `TemplateProcessing` post-processor becomes a
+//! [`PostProcessor`], other post-processors are ignored.
+
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::byte_level;
+use crate::error::{Error, Result};
+use crate::json::{flag, invalid, read_json, token_id, write_json};
+use crate::normalizer::Normalizer;
+use crate::post_processor::PostProcessor;
+use crate::pre_tokenizer::{PreTokenizer, RegexPreTokenizer, GPT2_PATTERN};
+use crate::vocabulary::{Merge, Vocabulary};
+
+impl Vocabulary {
+    /// Reads a Hugging Face `tokenizer.json` file.
+    pub fn load_hf_tokenizer(path: impl AsRef<Path>) -> Result<Vocabulary> {
+        Vocabulary::from_hf_tokenizer_json(&read_json(path.as_ref())?)
+    }
+
+    /// Writes the vocabulary as a Hugging Face `tokenizer.json` file.
+    pub fn save_hf_tokenizer(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(path.as_ref(), &self.to_hf_tokenizer_json())
+    }
+
+    /// Converts a parsed Hugging Face `tokenizer.json` into a vocabulary.
+    pub fn from_hf_tokenizer_json(value: &Value) -> Result<Vocabulary> {
+        let model = &value["model"];
+        check_model(model)?;
+        let normalizer = match &value["normalizer"] {
+            Value::Null => None,
+            normalizer => Some(Normalizer::from_json(normalizer)?),
+        };
+        let (add_prefix_space, pre_tokenizer) = parse_pre_tokenizer(&value["pre_tokenizer"])?;
+
+        let vocab = model["vocab"]
+            .as_object()
+            .ok_or_else(|| invalid("model.vocab must be an object"))?;
+        let added_tokens = value["added_tokens"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice);
+        // Ids are dense, so none can reach the number of entries.
+        let len = vocab.len() + added_tokens.len();
+        let mut tokens = parse_vocab(vocab, len)?;
+
+        let mut special_tokens = Vec::new();
+        let mut added_ids = Vec::new();
+        for added in added_tokens {
+            let content = added["content"]
+                .as_str()
+                .ok_or_else(|| invalid("added token without content"))?;
+            let id = token_id(&added["id"])?;
+            match tokens.get(id as usize) {
+                Some(Some(bytes)) if bytes.as_slice() != content.as_bytes() => {
+                    return Err(invalid(format!(
+                        "added token {content:?} conflicts with id {id}"
+                    )))
+                }
+                Some(Some(_)) => {}
+                _ => place(&mut tokens, &added["id"], content.as_bytes().to_vec(), len)?,
+            }
+            match flag(&added["special"], false)? {
+                true => special_tokens.push(id),
+                false => added_ids.push(id),
+            }
+        }
+        special_tokens.sort_unstable();
+        special_tokens.dedup();
+
+        let merges = model["merges"]
+            .as_array()
+            .ok_or_else(|| invalid("model.merges must be an array"))?
+            .iter()
+            .map(|merge| match merge {
+                Value::String(merge) => {
+                    let (left, right) = merge.split_once(' ').unwrap_or((merge, ""));
+                    parse_merge(vocab, left, right)
+                }
+                Value::Array(pair) if pair.len() == 2 => parse_merge(
+                    vocab,
+                    pair[0].as_str().unwrap_or_default(),
+                    pair[1].as_str().unwrap_or_default(),
+                ),
+                _ => Err(invalid(format!("invalid merge {merge}"))),
+            })
+            .collect::<Result<_>>()?;
+
+        let mut vocabulary =
+            Vocabulary::from_parts(collect_tokens(tokens)?, merges, special_tokens)?;
+        if let Some(normalizer) = normalizer {
+            vocabulary.set_normalizer(normalizer);
+        }
+        vocabulary.set_added_tokens(added_ids)?;
+        vocabulary.set_add_prefix_space(add_prefix_space);
+        if let Some(pre_tokenizer) = pre_tokenizer {
+            vocabulary.set_pre_tokenizer(pre_tokenizer);
+        }
+        if let Some(post_processor) = PostProcessor::from_hf_json(&value["post_processor"])? {
+            vocabulary.set_post_processor(post_processor)?;
+        }
+        Ok(vocabulary)
+    }
+
+    /// The vocabulary as a Hugging Face `tokenizer.json` value.
+    ///
+    /// Custom pre-tokenizers without a [description](PreTokenizer::to_json) are left out.
+    pub fn to_hf_tokenizer_json(&self) -> Value {
+        let mut added_ids: Vec<u32> = [self.special_tokens(), self.added_tokens()].concat();
+        added_ids.sort_unstable();
+        let added_tokens: Vec<Value> = added_ids
+            .into_iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "content": String::from_utf8_lossy(self.token(id).unwrap()),
+                    "single_word": false,
+                    "lstrip": false,
+                    "rstrip": false,
+                    "normalized": false,
+                    "special": self.special_tokens().contains(&id),
+                })
+            })
+            .collect();
+        let merges: Vec<Value> = byte_level_merges(self)
+            .map(|(left, right)| json!(format!("{left} {right}")))
+            .collect();
+
+        json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": added_tokens,
+            "normalizer": self.normalizer().map(Normalizer::to_json),
+            "pre_tokenizer": pre_tokenizer_json(self),
+            "post_processor": match self.post_processor() {
+                Some(post_processor) => post_processor.to_hf_json(self),
+                None => json!({
+                    "type": "ByteLevel",
+                    "add_prefix_space": true,
+                    "trim_offsets": false,
+                    "use_regex": true,
+                }),
+            },
+            "decoder": {
+                "type": "ByteLevel",
+                "add_prefix_space": true,
+                "trim_offsets": true,
+                "use_regex": true,
+            },
+            "model": {
+                "type": "BPE",
+                "dropout": null,
+                "unk_token": null,
+                "continuing_subword_prefix": null,
+                "end_of_word_suffix": null,
+                "fuse_unk": false,
+                "byte_fallback": false,
+                "ignore_merges": false,
+                "vocab": byte_level_vocab(self, false),
+                "merges": merges,
+            },
+        })
+    }
+}
+
+/// Reads the `add_prefix_space` flag and the regex pre-tokenizer of a `pre_tokenizer` entry.
+fn parse_pre_tokenizer(value: &Value) -> Result<(bool, Option<RegexPreTokenizer>)> {
+    let byte_level = |value: &Value| flag(&value["add_prefix_space"], true);
+    match value["type"].as_str() {
+        _ if value.is_null() => Ok((false, None)),
+        Some("ByteLevel") => {
+            let use_regex = flag(&value["use_regex"], true)?;
+            Ok((byte_level(value)?, use_regex.then(RegexPreTokenizer::gpt2)))
+        }
+        Some("Sequence") => match value["pretokenizers"].as_array().map(Vec::as_slice) {
+            Some([split, last])
+                if split["type"] == "Split"
+                    && split["behavior"] == "Isolated"
+                    && !flag(&split["invert"], false)?
+                    && last["type"] == "ByteLevel"
+                    && !flag(&last["use_regex"], true)? =>
+            {
+                let pattern = split["pattern"]["Regex"]
+                    .as_str()
+                    .ok_or_else(|| unsupported("split on a string pattern"))?;
+                Ok((byte_level(last)?, Some(RegexPreTokenizer::new(pattern)?)))
+            }
+            _ => Err(unsupported("pre-tokenizer sequence")),
+        },
+        _ => Err(unsupported(format!("pre-tokenizer {}", value["type"]))),
+    }
+}
+
+/// The `pre_tokenizer` entry for the pre-tokenization settings of `vocabulary`.
+fn pre_tokenizer_json(vocabulary: &Vocabulary) -> Value {
+    let byte_level = |use_regex: bool| {
+        json!({
+            "type": "ByteLevel",
+            "add_prefix_space": vocabulary.add_prefix_space(),
+            "trim_offsets": true,
+            "use_regex": use_regex,
+        })
+    };
+    let description = vocabulary.pre_tokenizer().and_then(PreTokenizer::to_json);
+    let pattern = match &description {
+        Some(description) if description["type"] == "Whitespace" => r"\s+|\S+",
+        Some(description) => match description["pattern"].as_str() {
+            Some(GPT2_PATTERN) => return byte_level(true),
+            Some(pattern) => pattern,
+            None => return byte_level(false),
+        },
+        None => return byte_level(false),
+    };
+    json!({
+        "type": "Sequence",
+        "pretokenizers": [
+            {
+                "type": "Split",
+                "pattern": { "Regex": pattern },
+                "behavior": "Isolated",
+                "invert": false,
+            },
+            byte_level(false),
+        ],
+    })
+}
+
+/// Rejects model options that change how merges are applied.
+fn check_model(model: &Value) -> Result<()> {
+    if !model["type"].is_null() && model["type"] != "BPE" {
+        return Err(unsupported(format!("model type {}", model["type"])));
+    }
+    if model["dropout"]
+        .as_f64()
+        .is_some_and(|dropout| dropout > 0.0)
+    {
+        return Err(unsupported("BPE dropout"));
+    }
+    for option in ["continuing_subword_prefix", "end_of_word_suffix"] {
+        if model[option]
+            .as_str()
+            .is_some_and(|affix| !affix.is_empty())
+        {
+            return Err(unsupported(option));
+        }
+    }
+    for option in ["byte_fallback", "ignore_merges"] {
+        if flag(&model[option], false)? {
+            return Err(unsupported(option));
+        }
+    }
+    Ok(())
+}
+
+/// Reads a byte-level `vocab` object into the token bytes indexed by id, which must be below
+/// `len`.
+pub(crate) fn parse_vocab(vocab: &Map<String, Value>, len: usize) -> Result<Vec<Option<Vec<u8>>>> {
+    let mut tokens = Vec::new();
+    for (key, id) in vocab {
+        let bytes = byte_level::decode(key)
+            .ok_or_else(|| unsupported(format!("token {key:?} is not byte-level")))?;
+        place(&mut tokens, id, bytes, len)?;
+    }
+    Ok(tokens)
+}
+
+/// Resolves the merge of the byte-level tokens `left` and `right` against `vocab`.
+pub(crate) fn parse_merge(vocab: &Map<String, Value>, left: &str, right: &str) -> Result<Merge> {
+    let lookup = |token: &str| {
+        vocab
+            .get(token)
+            .ok_or_else(|| invalid(format!("merge {left:?} {right:?} refers to unknown tokens")))
+            .and_then(token_id)
+    };
+    Ok(Merge {
+        left: lookup(left)?,
+        right: lookup(right)?,
+        id: lookup(&format!("{left}{right}"))?,
+    })
+}
+
+/// Checks that every id up to the largest one has a token.
+pub(crate) fn collect_tokens(tokens: Vec<Option<Vec<u8>>>) -> Result<Vec<Vec<u8>>> {
+    tokens
+        .into_iter()
+        .enumerate()
+        .map(|(id, token)| token.ok_or_else(|| invalid(format!("token id {id} is missing"))))
+        .collect()
+}
+
+/// Byte-level spelling of every token, special tokens only if `with_special` is set.
+pub(crate) fn byte_level_vocab(vocabulary: &Vocabulary, with_special: bool) -> Map<String, Value> {
+    (0..vocabulary.len() as u32)
+        .filter(|id| with_special || !vocabulary.special_tokens().contains(id))
+        .map(|id| (byte_level::encode(vocabulary.token(id).unwrap()), json!(id)))
+        .collect()
+}
+
+/// Byte-level spelling of every merge, in rank order.
+pub(crate) fn byte_level_merges(
+    vocabulary: &Vocabulary,
+) -> impl Iterator<Item = (String, String)> + '_ {
+    vocabulary.merges().iter().map(|merge| {
+        (
+            byte_level::encode(vocabulary.token(merge.left).unwrap()),
+            byte_level::encode(vocabulary.token(merge.right).unwrap()),
+        )
+    })
+}
+
+/// Stores `bytes` at the id given by `id`, growing `tokens` as needed. Ids from `len` on are
+/// rejected, so a corrupt file cannot make `tokens` arbitrarily large.
+pub(crate) fn place(
+    tokens: &mut Vec<Option<Vec<u8>>>,
+    id: &Value,
+    bytes: Vec<u8>,
+    len: usize,
+) -> Result<()> {
+    let id = token_id(id)? as usize;
+    if id >= len {
+        return Err(invalid(format!("token id {id} is out of range")));
+    }
+    if id >= tokens.len() {
+        tokens.resize(id + 1, None);
+    }
+    match &mut tokens[id] {
+        slot @ None => *slot = Some(bytes),
+        Some(_) => return Err(invalid(format!("token id {id} is repeated"))),
+    }
+    Ok(())
+}
+
+fn unsupported(feature: impl Into<String>) -> Error {
+    Error::Unsupported(feature.into())
+}
@@ -0,0 +1,226 @@
+//! Canonical Huffman coding of symbol streams.
+//!
+//! Symbols are numbered most frequent first and get non-decreasing code lengths, so a code is
+//! fully described by the number of codes of every length. Codes of one length are consecutive
+//! integers and the first code of a length follows the last code of the previous length, as in
+//! DEFLATE. Bits are packed most significant first.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::error::{Error, Result};
+use crate::varint::{self, Reader};
+
+/// Longest code. Streams with more symbols than `1 << MAX_CODE_LEN` cannot be coded.
+pub(crate) const MAX_CODE_LEN: usize = 24;
+
+/// Code lengths for symbols with the given `frequencies`, which must be sorted in decreasing
+/// order. The lengths are non-decreasing and at most [`MAX_CODE_LEN`].
+///
+/// When the optimal code is too long, the frequencies are halved until it fits. This costs
+/// little since it only happens for symbols that are very rare compared to the others.
+pub(crate) fn code_lengths(frequencies: &[u64]) -> Vec<u8> {
+    assert!(frequencies.len() <= 1 << MAX_CODE_LEN);
+    if frequencies.len() < 2 {
+        return vec![1; frequencies.len()];
+    }
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let mut lengths = optimal_lengths(&frequencies);
+        // Giving shorter codes to more frequent symbols never makes the output longer.
+        lengths.sort_unstable();
+        if usize::from(lengths[lengths.len() - 1]) <= MAX_CODE_LEN {
+            return lengths;
+        }
+        for frequency in &mut frequencies {
+            *frequency = (*frequency >> 1).max(1);
+        }
+    }
+}
+
+/// Depths of the leaves of a Huffman tree over at least two `frequencies`.
+fn optimal_lengths(frequencies: &[u64]) -> Vec<u8> {
+    let leaves = frequencies.len();
+    let mut parent = vec![0; 2 * leaves - 1];
+    let mut queue: BinaryHeap<Reverse<(u64, usize)>> = frequencies
+        .iter()
+        .enumerate()
+        .map(|(node, &frequency)| Reverse((frequency, node)))
+        .collect();
+    let mut next = leaves;
+    while let (Some(Reverse((a, left))), Some(Reverse((b, right)))) = (queue.pop(), queue.pop()) {
+        parent[left] = next;
+        parent[right] = next;
+        queue.push(Reverse((a + b, next)));
+        next += 1;
+    }
+    // Parents are created after their children, so walking down from the root sees every
+    // parent before its children.
+    let root = 2 * leaves - 2;
+    let mut depth = vec![0u8; 2 * leaves - 1];
+    for node in (0..root).rev() {
+        depth[node] = depth[parent[node]] + 1;
+    }
+    depth.truncate(leaves);
+    depth
+}
+
+/// Writes the number of codes of every length: the longest length, then one count per length.
+pub(crate) fn write_lengths(out: &mut Vec<u8>, lengths: &[u8]) {
+    let max_len = lengths.last().map_or(0, |&len| usize::from(len));
+    varint::write(out, max_len as u64);
+    let mut counts = vec![0u64; max_len + 1];
+    for &len in lengths {
+        counts[usize::from(len)] += 1;
+    }
+    for &count in &counts[1..] {
+        varint::write(out, count);
+    }
+}
+
+/// Maps symbols to their codes.
+pub(crate) struct Encoder {
+    codes: Vec<(u32, u8)>,
+}
+
+impl Encoder {
+    /// Encoder for the non-decreasing code `lengths` of [`code_lengths`].
+    pub(crate) fn new(lengths: &[u8]) -> Encoder {
+        let mut codes = Vec::with_capacity(lengths.len());
+        let mut code = 0u32;
+        let mut previous = lengths.first().copied().unwrap_or(0);
+        for &len in lengths {
+            code <<= len - previous;
+            codes.push((code, len));
+            code += 1;
+            previous = len;
+        }
+        Encoder { codes }
+    }
+
+    pub(crate) fn write(&self, writer: &mut BitWriter, symbol: usize) {
+        let (code, len) = self.codes[symbol];
+        writer.write(code, len);
+    }
+}
+
+/// Maps codes back to symbols.
+pub(crate) struct Decoder {
+    /// Number of codes of every length, indexed by length.
+    counts: Vec<u32>,
+}
+
+impl Decoder {
+    /// Reads the counts written by [`write_lengths`] for a code of `symbols` symbols.
+    pub(crate) fn read(reader: &mut Reader<'_>, symbols: usize) -> Result<Decoder> {
+        let max_len = reader.varint_usize()?;
+        if max_len > MAX_CODE_LEN {
+            return Err(invalid(format!("code length {max_len} is too long")));
+        }
+        let mut counts = vec![0u32; max_len + 1];
+        // Codes still available at the current length, which must never become negative.
+        let mut available = 1i64;
+        for count in &mut counts[1..] {
+            *count = reader.varint_u32()?;
+            available = 2 * available - i64::from(*count);
+            if available < 0 {
+                return Err(invalid("more codes than fit their lengths".into()));
+            }
+        }
+        let total: u64 = counts.iter().map(|&count| u64::from(count)).sum();
+        if total != symbols as u64 {
+            return Err(invalid(format!("{total} codes for {symbols} symbols")));
+        }
+        Ok(Decoder { counts })
+    }
+
+    /// Reads one code and returns its symbol.
+    pub(crate) fn decode(&self, reader: &mut BitReader<'_>) -> Result<usize> {
+        let mut code = 0u32;
+        let mut first = 0u32;
+        let mut index = 0u32;
+        for &count in &self.counts[1..] {
+            code |= reader.bit()?;
+            // Unused codes are the largest of their length, so `code` is never below `first`.
+            if code - first < count {
+                return Ok((index + code - first) as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("unused Huffman code".into()))
+    }
+}
+
+/// Packs codes into bytes, most significant bit first.
+pub(crate) struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    buffer: u64,
+    bits: u8,
+}
+
+impl<'a> BitWriter<'a> {
+    pub(crate) fn new(out: &'a mut Vec<u8>) -> BitWriter<'a> {
+        BitWriter {
+            out,
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, code: u32, len: u8) {
+        self.buffer = self.buffer << len | u64::from(code);
+        self.bits += len;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.out.push((self.buffer >> self.bits) as u8);
+        }
+    }
+
+    /// Writes the last bits, padded with zeros to a whole byte.
+    pub(crate) fn finish(mut self) {
+        if self.bits > 0 {
+            let padding = 8 - self.bits;
+            self.write(0, padding);
+        }
+    }
+}
+
+/// Reads the bits of a [`BitWriter`].
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, position: 0 }
+    }
+
+    fn bit(&mut self) -> Result<u32> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or_else(|| invalid("unexpected end of data".into()))?;
+        let bit = byte >> (7 - self.position % 8) & 1;
+        self.position += 1;
+        Ok(u32::from(bit))
+    }
+
+    /// Checks that only the zero padding of [`BitWriter::finish`] is left.
+    pub(crate) fn finish(self) -> Result<()> {
+        let padding = self
+            .data
+            .get(self.position / 8)
+            .map_or(0, |&byte| byte & (0xff >> (self.position % 8)));
+        if self.position.div_ceil(8) != self.data.len() || padding != 0 {
+            return Err(invalid("trailing bytes after the token stream".into()));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidCompressedData(message)
+}
@@ -0,0 +1,234 @@
+//! Saving and loading a [`Vocabulary`] as JSON.
+//!
+//! The file is a single object:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "vocab": { "a": 97, "b": 98, "ab": 256, "Ġthe": 257 },
+//!   "merges": [[97, 98, 256], [32, 116, 258]],
+//!   "special_tokens": { "<|endoftext|>": 300 },
+//!   "added_tokens": [257],
+//!   "normalizer": { "type": "NFC" },
+//!   "add_prefix_space": false,
+//!   "pre_tokenizer": { "type": "Regex", "pattern": "'s|'t|'re|'ve|'m|'ll|'d| ?\\p{L}+|..." },
+//!   "merge_mode": "merges",
+//!   "post_processor": { "type": "Template", "template": "<s> $A </s> $B </s>" }
+//! }
+//! ```
+//!
+//! - `version` is the schema version, currently `1`. Files with any other version are rejected.
+//! - `vocab` maps every regular token to its id. Token bytes are spelled with the GPT-2
+//!   byte-to-unicode table so arbitrary bytes stay readable, e.g. a space becomes `Ġ`.
+//! - `merges` lists `[left, right, id]` in rank order.
+//! - `special_tokens` maps the content of every special token to its id.
+//! - `added_tokens` lists the ids of the [added tokens](Vocabulary::added_tokens). It is
+//!   optional and defaults to none.
+//! - `normalizer` is the [`Normalizer::to_json`] form of the normalizer, `null` or missing if
+//!   the input is not normalized.
+//! - `add_prefix_space` is optional and defaults to `false`.
+//! - `pre_tokenizer` is the [`PreTokenizer::to_json`](crate::PreTokenizer::to_json)
+//!   description of the pre-tokenizer, either `{"type": "Whitespace"}` or
+//!   `{"type": "Regex", "pattern": ...}`. It is `null` or missing if the input is merged as a
+//!   whole. Vocabularies with a pre-tokenizer that has no description cannot be saved.
+//! - `merge_mode` is the [`MergeMode`], `"merges"` or `"ranks"`. It is optional and defaults
+//!   to `"merges"`.
+//! - `post_processor` holds the [`PostProcessor`] template, and the separate template for
+//!   pairs as `pair` if there is one. It is `null` or missing if no special tokens are added to
+//!   encoded inputs.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::byte_level;
+use crate::error::{Error, Result};
+use crate::normalizer::Normalizer;
+use crate::post_processor::PostProcessor;
+use crate::pre_tokenizer;
+use crate::vocabulary::{Merge, MergeMode, Vocabulary};
+
+/// Schema version written by [`Vocabulary::save`].
+pub const FORMAT_VERSION: u64 = 1;
+
+impl Vocabulary {
+    /// Writes the vocabulary to `path` as JSON.
+    ///
+    /// ```rust,no_run
+    /// use rust_bpe::{Vocabulary, BPE};
+    ///
+    /// let vocabulary = BPE::new().build("abababcab");
+    /// vocabulary.save("vocabulary.json").unwrap();
+    /// assert_eq!(Vocabulary::load("vocabulary.json").unwrap(), vocabulary);
+    /// ```
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(path.as_ref(), &self.to_json()?)
+    }
+
+    /// Reads a vocabulary written by [`save`](Vocabulary::save).
+    pub fn load(path: impl AsRef<Path>) -> Result<Vocabulary> {
+        Vocabulary::from_json(&read_json(path.as_ref())?)
+    }
+
+    /// The vocabulary as a JSON value in the versioned schema.
+    ///
+    /// Fails if the pre-tokenizer has no [description](crate::PreTokenizer::to_json), since the
+    /// vocabulary would split its input differently once it is read back.
+    pub fn to_json(&self) -> Result<Value> {
+        let mut vocab = Map::new();
+        let mut special_tokens = Map::new();
+        for id in 0..self.len() as u32 {
+            let token = self.token(id).unwrap();
+            if self.special_tokens().contains(&id) {
+                let content = String::from_utf8_lossy(token).into_owned();
+                special_tokens.insert(content, json!(id));
+            } else {
+                vocab.insert(byte_level::encode(token), json!(id));
+            }
+        }
+        let merges: Vec<Value> = self
+            .merges()
+            .iter()
+            .map(|merge| json!([merge.left, merge.right, merge.id]))
+            .collect();
+
+        let pre_tokenizer = match self.pre_tokenizer() {
+            Some(pre_tokenizer) => Some(
+                pre_tokenizer
+                    .to_json()
+                    .ok_or_else(|| invalid("pre-tokenizer cannot be saved"))?,
+            ),
+            None => None,
+        };
+
+        Ok(json!({
+            "version": FORMAT_VERSION,
+            "vocab": vocab,
+            "merges": merges,
+            "special_tokens": special_tokens,
+            "added_tokens": self.added_tokens(),
+            "normalizer": self.normalizer().map(Normalizer::to_json),
+            "add_prefix_space": self.add_prefix_space(),
+            "pre_tokenizer": pre_tokenizer,
+            "merge_mode": match self.merge_mode() {
+                MergeMode::Merges => "merges",
+                MergeMode::Ranks => "ranks",
+            },
+            "post_processor": self.post_processor().map(PostProcessor::to_json),
+        }))
+    }
+
+    /// Parses a JSON value in the versioned schema.
+    pub fn from_json(value: &Value) -> Result<Vocabulary> {
+        let version = value["version"]
+            .as_u64()
+            .ok_or_else(|| invalid("missing version"))?;
+        if version != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let vocab = object(&value["vocab"], "vocab")?;
+        let special = object(&value["special_tokens"], "special_tokens")?;
+        let mut tokens = vec![None; vocab.len() + special.len()];
+        let mut place = |id: &Value, bytes: Vec<u8>| {
+            let id = token_id(id)?;
+            match tokens.get_mut(id as usize) {
+                Some(slot @ None) => *slot = Some(bytes),
+                _ => {
+                    return Err(invalid(format!(
+                        "token id {id} is out of range or repeated"
+                    )))
+                }
+            }
+            Ok(id)
+        };
+        for (key, id) in vocab {
+            let bytes =
+                byte_level::decode(key).ok_or_else(|| invalid(format!("invalid token {key:?}")))?;
+            place(id, bytes)?;
+        }
+        let mut special_tokens = special
+            .iter()
+            .map(|(content, id)| place(id, content.as_bytes().to_vec()))
+            .collect::<Result<Vec<_>>>()?;
+        special_tokens.sort_unstable();
+        let tokens = tokens.into_iter().map(Option::unwrap).collect();
+
+        let merges = value["merges"]
+            .as_array()
+            .ok_or_else(|| invalid("merges must be an array"))?
+            .iter()
+            .map(|merge| match merge.as_array().map(Vec::as_slice) {
+                Some([left, right, id]) => Ok(Merge {
+                    left: token_id(left)?,
+                    right: token_id(right)?,
+                    id: token_id(id)?,
+                }),
+                _ => Err(invalid("merges must be [left, right, id] triples")),
+            })
+            .collect::<Result<_>>()?;
+
+        let mut vocabulary = Vocabulary::from_parts(tokens, merges, special_tokens)?;
+        let added_tokens = match &value["added_tokens"] {
+            Value::Null => Vec::new(),
+            Value::Array(ids) => ids.iter().map(token_id).collect::<Result<_>>()?,
+            _ => return Err(invalid("added_tokens must be an array")),
+        };
+        vocabulary.set_added_tokens(added_tokens)?;
+        if !value["normalizer"].is_null() {
+            vocabulary.set_normalizer(Normalizer::from_json(&value["normalizer"])?);
+        }
+        vocabulary.set_add_prefix_space(flag(&value["add_prefix_space"], false)?);
+        vocabulary.set_shared_pre_tokenizer(pre_tokenizer::from_json(&value["pre_tokenizer"])?);
+        vocabulary.set_merge_mode(match &value["merge_mode"] {
+            Value::Null => MergeMode::Merges,
+            mode if mode == "merges" => MergeMode::Merges,
+            mode if mode == "ranks" => MergeMode::Ranks,
+            mode => return Err(invalid(format!("unknown merge mode {mode}"))),
+        });
+        if !value["post_processor"].is_null() {
+            vocabulary.set_post_processor(PostProcessor::from_json(&value["post_processor"])?)?;
+        }
+        Ok(vocabulary)
+    }
+}
+
+pub(crate) fn read_json(path: &Path) -> Result<Value> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+pub(crate) fn write_json(path: &Path, value: &Value) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads an optional boolean, `default` if it is missing.
+pub(crate) fn flag(value: &Value, default: bool) -> Result<bool> {
+    match value {
+        Value::Null => Ok(default),
+        Value::Bool(flag) => Ok(*flag),
+        _ => Err(invalid(format!("expected a boolean, found {value}"))),
+    }
+}
+
+pub(crate) fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidVocabulary(message.into())
+}
+
+fn object<'a>(value: &'a Value, name: &str) -> Result<&'a Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| invalid(format!("{name} must be an object")))
+}
+
+pub(crate) fn token_id(value: &Value) -> Result<u32> {
+    value
+        .as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| invalid(format!("invalid token id {value}")))
+}
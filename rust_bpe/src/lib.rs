@@ -10,6 +10,7 @@
 //!
 //! - [Installation](#installation)
 //! - [Usage](#usage)
+//! - [Features](#features)
 //! - [License](#license)
 //!
 //! ## Installation
@@ -25,59 +26,656 @@
 //!
 //! ```rust
 //! use rust_bpe::BPE;
-//! 
-//! let mut bpe = BPE::new(); 
+//!
+//! let bpe = BPE::new();
 //! ```
 //!
 //! ### Compression
 //!
-//! To compress something using BPE, first build the vocabulary.
+//! To compress something using BPE, first build the vocabulary. Training files are streamed,
+//! so they do not need to fit into memory.
 //!
-//! ```rust
-//! let learn_file = File::open("input.txt").unwrap();
-//! let mut buf_reader = BufReader::new(learn_file);
-//! let mut data = String::new();
-//! buf_reader.read_to_string(&mut data).unwrap();
-//! let vocabulary = bpe.build(&file);
+//! ```rust,no_run
+//! # let bpe = rust_bpe::BPE::new();
+//! let vocabulary = bpe.build_from_files(["input.txt"]).unwrap();
 //! ```
 //!
 //! Then, use the encode method to encode the file into tokens:
 //!
-//! ```rust
+//! ```rust,no_run
+//! # use std::fs::File;
+//! # use std::io::{BufReader, Read};
+//! # let bpe = rust_bpe::BPE::new();
+//! # let vocabulary = bpe.build("");
 //! let input_file = File::open("input.txt").unwrap();
-//! let tokens = bpe.encode(&input_file);
+//! let mut buf_reader = BufReader::new(input_file);
+//! let mut data = String::new();
+//! buf_reader.read_to_string(&mut data).unwrap();
+//! let tokens = bpe.encode(&data, &vocabulary);
 //! ```
 //!
 //! The resulting `tokens` variable will contain the compressed representation of the input file.
+//! [`StreamEncoder`] produces the same tokens from a reader without loading the whole file, and
+//! [`StreamDecoder`] writes decoded tokens out as they arrive.
 //!
 //! ### Decompression
 //!
 //! To decompress the compressed file back into the original text, use the decode method:
 //!
 //! ```rust
-//! let decoded = bpe.decode(&tokens);
+//! # let bpe = rust_bpe::BPE::new();
+//! # let vocabulary = bpe.build("abababcab");
+//! # let tokens = bpe.encode("abcab", &vocabulary);
+//! let decoded = bpe.decode(&tokens, &vocabulary).unwrap();
+//! # assert_eq!(decoded, b"abcab");
 //! ```
 //!
-//! The decoded variable will now contain the original text.
+//! The decoded variable will now contain the original bytes. Use `decode_to_string` to get the
+//! text back as a `String` instead.
+//!
+//! ### Compressed files
+//!
+//! [`Compressor`] does both steps on arbitrary bytes and stores the learned merges with the
+//! tokens, so the output is a self-contained file that [`decompress`] restores. The tokens are
+//! Huffman coded by default, see [`EntropyCoding`]:
+//!
+//! ```rust
+//! use rust_bpe::{decompress, Compressor};
+//!
+//! let data = b"\x00\x01binary\xff data\x00\x01binary\xff data";
+//! let compressed = Compressor::new().min_frequency(2).compress(data);
+//! assert_eq!(decompress(compressed.as_bytes()).unwrap(), data);
+//! ```
+//!
+//! ## Features
+//!
+//! - `parallel`: counts words and pairs during training on the [rayon](https://docs.rs/rayon)
+//!   thread pool. The learned vocabulary is identical to the single-threaded one, whatever the
+//!   number of threads. [`BPE::encode_batch`] and [`BPE::decode_batch`] also process their
+//!   inputs in parallel.
+//! - `cli` (default): builds the `rust_bpe` binary with the `train`, `encode`, `decode`,
+//!   `inspect`, `convert`, `compress` and `decompress` subcommands. Run `rust_bpe help` for
+//!   their options. Library users can turn it off with `default-features = false` to avoid the
+//!   `clap` dependency.
 //!
 //! ## License
 //!
 //! This Rust BPE library is licensed under the [MIT License](https://opensource.org/licenses/MIT). Feel free to use, modify, and distribute it as you like.
 
+mod byte_level;
+mod cache;
+mod compress;
+mod corpus;
+mod encode;
+mod encoding;
+mod error;
+mod gage;
+mod gpt2;
+mod hf;
+mod huffman;
+mod json;
+mod normalizer;
+mod padding;
+mod post_processor;
+mod pre_tokenizer;
+mod special;
+mod stream;
+mod tiktoken;
+mod train;
+mod truncation;
+mod varint;
+mod vocabulary;
+
+pub use cache::CacheStats;
+pub use compress::{decompress, Compressed, Compressor, EntropyCoding};
+pub use encode::EncodeStrategy;
+pub use encoding::Encoding;
+pub use error::{Error, Result};
+pub use gage::GageCompressor;
+pub use json::FORMAT_VERSION;
+pub use normalizer::Normalizer;
+pub use padding::{Padding, PaddingLength};
+pub use post_processor::PostProcessor;
+pub use pre_tokenizer::{
+    PreTokenizer, RegexPreTokenizer, Whitespace, CL100K_PATTERN, GPT2_PATTERN,
+};
+pub use special::AllowedSpecial;
+pub use stream::{StreamDecoder, StreamEncoder};
+pub use train::BpeTrainer;
+pub use truncation::{Side, Truncation, TruncationStrategy};
+pub use vocabulary::{Merge, MergeMode, Vocabulary};
 
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
 
-pub struct BPE;
+use corpus::WordCounter;
 
-pub struct Vocabulary;
+pub struct BPE {
+    trainer: BpeTrainer,
+    normalizer: Option<Normalizer>,
+    pre_tokenizer: Option<pre_tokenizer::Shared>,
+    allowed_special: AllowedSpecial,
+    encode_strategy: EncodeStrategy,
+    cache: Option<cache::WordCache>,
+    truncation: Option<Truncation>,
+    padding: Option<Padding>,
+    add_special_tokens: bool,
+    skip_special_tokens: bool,
+}
 
 impl BPE {
     pub fn new() -> BPE {
-        BPE
+        BPE::with_trainer(BpeTrainer::new())
+    }
+
+    /// BPE whose [`build`](BPE::build) trains with the given configuration.
+    pub fn with_trainer(trainer: BpeTrainer) -> BPE {
+        BPE {
+            trainer,
+            normalizer: None,
+            pre_tokenizer: None,
+            allowed_special: AllowedSpecial::None,
+            encode_strategy: EncodeStrategy::Reference,
+            cache: None,
+            truncation: None,
+            padding: None,
+            add_special_tokens: true,
+            skip_special_tokens: false,
+        }
+    }
+
+    /// Training configuration used by [`build`](BPE::build).
+    pub fn trainer(&self) -> &BpeTrainer {
+        &self.trainer
+    }
+
+    /// Normalizes the training data of [`build`](BPE::build) with `normalizer`.
+    ///
+    /// The normalizer is recorded in the built vocabulary, so [`encode`](BPE::encode) normalizes
+    /// its input the same way.
+    ///
+    /// ```rust
+    /// use rust_bpe::{Normalizer, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// bpe.set_normalizer(Normalizer::Nfc);
+    /// let vocabulary = bpe.build("caf\u{e9} cafe\u{301}");
+    /// assert_eq!(
+    ///     bpe.encode("cafe\u{301}", &vocabulary),
+    ///     bpe.encode("caf\u{e9}", &vocabulary)
+    /// );
+    /// ```
+    pub fn set_normalizer(&mut self, normalizer: Normalizer) {
+        self.normalizer = Some(normalizer);
+    }
+
+    /// Normalizer applied by [`build`](BPE::build), `None` if the data is used as it is.
+    pub fn normalizer(&self) -> Option<&Normalizer> {
+        self.normalizer.as_ref()
+    }
+
+    /// Splits the training data of [`build`](BPE::build) with `pre_tokenizer`.
+    ///
+    /// The pre-tokenizer is recorded in the built vocabulary, so [`encode`](BPE::encode) splits
+    /// its input the same way.
+    ///
+    /// ```rust
+    /// use rust_bpe::{RegexPreTokenizer, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    /// let vocabulary = bpe.build("hello world hello world");
+    /// // Merges stay within the pieces "hello", " world", " hello" and " world".
+    /// assert_eq!(vocabulary.token_id(b"o w"), None);
+    /// assert_eq!(bpe.encode(" world", &vocabulary).len(), 1);
+    /// ```
+    pub fn set_pre_tokenizer(&mut self, pre_tokenizer: impl PreTokenizer + 'static) {
+        self.pre_tokenizer = Some(pre_tokenizer::Shared(Arc::new(pre_tokenizer)));
+    }
+
+    /// Pre-tokenizer applied by [`build`](BPE::build), `None` if the data is merged as a whole.
+    pub fn pre_tokenizer(&self) -> Option<&dyn PreTokenizer> {
+        self.pre_tokenizer.as_ref().map(|shared| &*shared.0)
+    }
+
+    /// Chooses which special tokens [`encode`](BPE::encode) recognizes in its input. By default
+    /// none are, their text is encoded like any other, so input cannot inject control tokens.
+    pub fn set_allowed_special(&mut self, allowed_special: AllowedSpecial) {
+        self.allowed_special = allowed_special;
+    }
+
+    /// Special tokens recognized by [`encode`](BPE::encode).
+    pub fn allowed_special(&self) -> &AllowedSpecial {
+        &self.allowed_special
+    }
+
+    /// Chooses how [`encode`](BPE::encode) applies the merges. The output is the same for every
+    /// strategy.
+    ///
+    /// ```rust
+    /// use rust_bpe::{EncodeStrategy, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// let vocabulary = bpe.build("abababcab");
+    /// let reference = bpe.encode("abcababab", &vocabulary);
+    /// bpe.set_encode_strategy(EncodeStrategy::Heap);
+    /// assert_eq!(bpe.encode("abcababab", &vocabulary), reference);
+    /// ```
+    pub fn set_encode_strategy(&mut self, encode_strategy: EncodeStrategy) {
+        self.encode_strategy = encode_strategy;
+    }
+
+    /// How [`encode`](BPE::encode) applies the merges.
+    pub fn encode_strategy(&self) -> EncodeStrategy {
+        self.encode_strategy
+    }
+
+    /// Remembers the ids of the `capacity` most recently encoded pre-tokenized pieces, so that
+    /// repeated words are merged only once. A capacity of `0` turns the cache off, which is the
+    /// default.
+    ///
+    /// The cache is shared by all threads of [`encode_batch`](BPE::encode_batch) and is emptied
+    /// when a different vocabulary is used. Pieces longer than 256 bytes are not cached.
+    ///
+    /// ```rust
+    /// use rust_bpe::{Whitespace, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// bpe.set_pre_tokenizer(Whitespace);
+    /// let vocabulary = bpe.build("the cat and the hat");
+    ///
+    /// bpe.set_cache_capacity(1_000);
+    /// bpe.encode("the cat the hat", &vocabulary);
+    /// let stats = bpe.cache_stats();
+    /// // Seven pieces, of which `the` and the space were seen before.
+    /// assert_eq!((stats.hits, stats.misses, stats.len), (3, 4, 4));
+    /// ```
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache = (capacity > 0).then(|| cache::WordCache::new(capacity));
+    }
+
+    /// Most pieces kept in the word cache, `0` if there is no cache.
+    pub fn cache_capacity(&self) -> usize {
+        self.cache.as_ref().map_or(0, cache::WordCache::capacity)
+    }
+
+    /// Hits, misses and size of the word cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache
+            .as_ref()
+            .map_or_else(CacheStats::default, cache::WordCache::stats)
+    }
+
+    /// Empties the word cache and resets its statistics.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Shortens the encodings of [`encode_with_offsets`](BPE::encode_with_offsets) and
+    /// [`encode_batch_with_offsets`](BPE::encode_batch_with_offsets) to a maximum length.
+    pub fn set_truncation(&mut self, truncation: Truncation) {
+        self.truncation = Some(truncation);
+    }
+
+    /// Keeps encodings at their full length.
+    pub fn remove_truncation(&mut self) {
+        self.truncation = None;
+    }
+
+    /// Truncation applied to encodings, `None` if they keep their full length.
+    pub fn truncation(&self) -> Option<&Truncation> {
+        self.truncation.as_ref()
+    }
+
+    /// Pads the encodings of [`encode_with_offsets`](BPE::encode_with_offsets) and
+    /// [`encode_batch_with_offsets`](BPE::encode_batch_with_offsets) after truncation.
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.padding = Some(padding);
+    }
+
+    /// Leaves encodings unpadded.
+    pub fn remove_padding(&mut self) {
+        self.padding = None;
+    }
+
+    /// Padding applied to encodings, `None` if they are not padded.
+    pub fn padding(&self) -> Option<&Padding> {
+        self.padding.as_ref()
+    }
+
+    /// Whether encoding applies the [`PostProcessor`] of the vocabulary, which is the default.
+    /// Turn this off to encode fragments of a larger input.
+    pub fn set_add_special_tokens(&mut self, add_special_tokens: bool) {
+        self.add_special_tokens = add_special_tokens;
+    }
+
+    /// Whether encoding applies the [`PostProcessor`] of the vocabulary.
+    pub fn add_special_tokens(&self) -> bool {
+        self.add_special_tokens
+    }
+
+    /// Leaves the special tokens out of [`decode`](BPE::decode), such as the ones added by a
+    /// [`PostProcessor`]. Off by default, so that decoding reproduces the input.
+    pub fn set_skip_special_tokens(&mut self, skip_special_tokens: bool) {
+        self.skip_special_tokens = skip_special_tokens;
+    }
+
+    /// Whether [`decode`](BPE::decode) leaves the special tokens out.
+    pub fn skip_special_tokens(&self) -> bool {
+        self.skip_special_tokens
+    }
+
+    /// Learns a vocabulary from `data`.
+    ///
+    /// The data is [normalized](BPE::set_normalizer) and split by the
+    /// [pre-tokenizer](BPE::set_pre_tokenizer) first, merges never cross the boundary between
+    /// two pieces. Occurrences of the special tokens of the [`BpeTrainer`] are left out.
+    /// Starting from the 256 single-byte tokens, the most frequent adjacent pair of tokens is
+    /// merged into a new token until the [`BpeTrainer`] limits are reached or no pair is frequent
+    /// enough anymore. Special tokens are reserved after the merges.
+    ///
+    /// ```rust
+    /// use rust_bpe::BPE;
+    ///
+    /// let vocabulary = BPE::new().build("abababcab");
+    /// assert_eq!(vocabulary.token(256), Some(&b"ab"[..]));
+    /// ```
+    pub fn build(&self, data: &str) -> Vocabulary {
+        let mut counter = self.word_counter();
+        counter.add(data.as_bytes());
+        self.train(counter)
+    }
+
+    /// Learns a vocabulary from a collection of documents, like [`build`](BPE::build) does.
+    ///
+    /// Documents are split independently and only their word counts are kept, so the
+    /// documents can be produced lazily.
+    ///
+    /// ```rust
+    /// use rust_bpe::BPE;
+    ///
+    /// let lines = "abab\nabc\n".lines();
+    /// let vocabulary = BPE::new().build_from_iter(lines);
+    /// assert_eq!(vocabulary.token(256), Some(&b"ab"[..]));
+    /// ```
+    pub fn build_from_iter<I>(&self, documents: I) -> Vocabulary
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut counter = self.word_counter();
+        for document in documents {
+            counter.add(document.as_ref());
+        }
+        self.train(counter)
+    }
+
+    /// Learns a vocabulary from everything `reader` produces, like [`build`](BPE::build) does.
+    ///
+    /// The input is read in chunks and split into the same words as [`build`](BPE::build)
+    /// splits it into. With the built-in pre-tokenizers, the words are counted a few pieces
+    /// behind the input, so memory is bounded by the number of distinct words instead of the
+    /// size of the input. Other pre-tokenizers may look arbitrarily far ahead, so like without a
+    /// pre-tokenizer the text between two special tokens is kept until it is complete.
+    pub fn build_from_reader(&self, reader: impl Read) -> Result<Vocabulary> {
+        let mut counter = self.word_counter();
+        counter.add_reader(reader)?;
+        Ok(self.train(counter))
+    }
+
+    /// Learns a vocabulary from the contents of the files at `paths`, read like
+    /// [`build_from_reader`](BPE::build_from_reader) does. Every file is a document of its own.
+    pub fn build_from_files<I>(&self, paths: I) -> Result<Vocabulary>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let mut counter = self.word_counter();
+        for path in paths {
+            counter.add_reader(File::open(path)?)?;
+        }
+        Ok(self.train(counter))
+    }
+
+    /// Counter for the words of training data, split the way this BPE encodes.
+    fn word_counter(&self) -> WordCounter<'_> {
+        WordCounter::new(
+            self.trainer.special_matcher(),
+            self.normalizer.as_ref(),
+            self.pre_tokenizer(),
+        )
     }
 
-    pub fn build(&self, data: &String) -> Vocabulary {
-        Vocabulary
+    /// Learns the merges on the counted words and records the splitting settings.
+    fn train(&self, counter: WordCounter<'_>) -> Vocabulary {
+        let mut vocabulary = self.trainer.train(counter.into_words());
+        if let Some(normalizer) = &self.normalizer {
+            vocabulary.set_normalizer(normalizer.clone());
+        }
+        vocabulary.set_shared_pre_tokenizer(self.pre_tokenizer.clone());
+        vocabulary
     }
 
-    pub fn encode(&self, )
-}
\ No newline at end of file
+    /// Encodes `data` into token ids of `vocabulary`.
+    ///
+    /// Special tokens permitted by [`allowed_special`](BPE::allowed_special) are matched first and
+    /// emitted as their ids. The text around them is normalized and split with the settings of
+    /// the vocabulary and the learned merges are applied to every piece in rank order, so
+    /// identical input always produces identical ids.
+    ///
+    /// ```rust
+    /// use rust_bpe::BPE;
+    ///
+    /// let bpe = BPE::new();
+    /// let vocabulary = bpe.build("abababcab");
+    /// assert_eq!(bpe.encode("abc", &vocabulary), vec![256, 99]);
+    /// assert_eq!(bpe.encode(b"\xffab", &vocabulary), vec![255, 256]);
+    /// ```
+    pub fn encode(&self, data: impl AsRef<[u8]>, vocabulary: &Vocabulary) -> Vec<u32> {
+        let ids = encode::encode(self, vocabulary, data.as_ref());
+        match self.post_processor(vocabulary) {
+            Some(post_processor) => post_processor.apply_ids(ids, vocabulary),
+            None => ids,
+        }
+    }
+
+    /// Encodes `data` like [`encode`](BPE::encode), also recording the position of every token.
+    ///
+    /// The offsets refer to `data` itself, also when the vocabulary normalizes it, so they can be
+    /// used to align the tokens with annotations of the original text.
+    ///
+    /// ```rust
+    /// use rust_bpe::{Normalizer, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// bpe.set_normalizer(Normalizer::Nfc);
+    /// let vocabulary = bpe.build("caf\u{e9}caf\u{e9}");
+    ///
+    /// let encoding = bpe.encode_with_offsets("cafe\u{301}!", &vocabulary);
+    /// assert_eq!(encoding.ids(), bpe.encode("cafe\u{301}!", &vocabulary));
+    /// assert_eq!(encoding.offsets().last(), Some(&(6..7)));
+    /// assert_eq!(encoding.char_offsets().last(), Some(&(5..6)));
+    /// ```
+    ///
+    /// The [`truncation`](BPE::truncation) and [`padding`](BPE::padding) settings are applied to
+    /// the result.
+    pub fn encode_with_offsets(&self, data: impl AsRef<[u8]>, vocabulary: &Vocabulary) -> Encoding {
+        let mut encoding = self.encode_processed(data.as_ref(), vocabulary);
+        if let Some(padding) = &self.padding {
+            padding.apply(std::slice::from_mut(&mut encoding), vocabulary);
+        }
+        encoding
+    }
+
+    /// Encodes every input like [`encode_with_offsets`](BPE::encode_with_offsets) does, keeping
+    /// their order, and pads them to a common length.
+    ///
+    /// This produces model-ready batches: with padding, all [`Encoding::ids`] have the same
+    /// length and [`Encoding::attention_mask`] tells the padding apart. With the `parallel`
+    /// feature the inputs are spread over the rayon thread pool.
+    pub fn encode_batch_with_offsets<S>(
+        &self,
+        inputs: &[S],
+        vocabulary: &Vocabulary,
+    ) -> Vec<Encoding>
+    where
+        S: AsRef<[u8]> + Sync,
+    {
+        let mut batch = map_batch(inputs, |data| {
+            self.encode_processed(data.as_ref(), vocabulary)
+        });
+        if let Some(padding) = &self.padding {
+            padding.apply(&mut batch, vocabulary);
+        }
+        batch
+    }
+
+    /// Encodes a pair of inputs, such as a question and its context, into one encoding.
+    ///
+    /// The tokens of `second` follow those of `first`. Their offsets and word ids refer to
+    /// `second`, so both inputs can be aligned with their own annotations. Truncation shortens
+    /// the pair as a whole according to its [`TruncationStrategy`], overflowing parts are
+    /// combined with the other sequence.
+    ///
+    /// ```rust
+    /// use rust_bpe::{Truncation, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// let vocabulary = bpe.build("");
+    /// bpe.set_truncation(Truncation::new(5));
+    ///
+    /// // The longer sequence is shortened first.
+    /// let encoding = bpe.encode_pair_with_offsets("ab", "cdefgh", &vocabulary);
+    /// assert_eq!(encoding.tokens().concat(), "abcde");
+    /// assert_eq!(encoding.offsets()[2], 0..1);
+    /// ```
+    pub fn encode_pair_with_offsets(
+        &self,
+        first: impl AsRef<[u8]>,
+        second: impl AsRef<[u8]>,
+        vocabulary: &Vocabulary,
+    ) -> Encoding {
+        let post_processor = self.post_processor(vocabulary);
+        let mut first = encode::encode_with_offsets(self, vocabulary, first.as_ref());
+        let mut second = encode::encode_with_offsets(self, vocabulary, second.as_ref());
+        if let Some(truncation) = &self.truncation {
+            let added = post_processor.map_or(0, |p| p.added_tokens(true));
+            truncation.apply_pair(&mut first, &mut second, added);
+        }
+        let mut encoding = post_processor::process(post_processor, first, Some(second), vocabulary);
+        if let Some(padding) = &self.padding {
+            padding.apply(std::slice::from_mut(&mut encoding), vocabulary);
+        }
+        encoding
+    }
+
+    /// The post-processor of `vocabulary`, unless adding special tokens is turned off.
+    fn post_processor<'v>(&self, vocabulary: &'v Vocabulary) -> Option<&'v PostProcessor> {
+        vocabulary
+            .post_processor()
+            .filter(|_| self.add_special_tokens)
+    }
+
+    /// Encodes, truncates and post-processes a single input.
+    fn encode_processed(&self, data: &[u8], vocabulary: &Vocabulary) -> Encoding {
+        let post_processor = self.post_processor(vocabulary);
+        let mut encoding = encode::encode_with_offsets(self, vocabulary, data);
+        if let Some(truncation) = &self.truncation {
+            truncation.apply(
+                &mut encoding,
+                post_processor.map_or(0, |p| p.added_tokens(false)),
+            );
+        }
+        post_processor::process(post_processor, encoding, None, vocabulary)
+    }
+
+    /// Decodes `tokens` back into the bytes they were encoded from.
+    ///
+    /// Every byte sequence survives a round trip: `decode(encode(x)) == x`. If the vocabulary has
+    /// a normalizer, the decoded bytes are the normalized input instead.
+    ///
+    /// ```rust
+    /// use rust_bpe::BPE;
+    ///
+    /// let bpe = BPE::new();
+    /// let vocabulary = bpe.build("abababcab");
+    /// let tokens = bpe.encode(b"\x00abab\xff", &vocabulary);
+    /// assert_eq!(bpe.decode(&tokens, &vocabulary).unwrap(), b"\x00abab\xff");
+    /// ```
+    pub fn decode(&self, tokens: &[u32], vocabulary: &Vocabulary) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(tokens.len());
+        for &id in tokens {
+            let token = vocabulary.token(id).ok_or(Error::UnknownToken(id))?;
+            if !(self.skip_special_tokens && vocabulary.special_tokens().contains(&id)) {
+                bytes.extend_from_slice(token);
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes `tokens` into a `String`, failing if the bytes are not valid UTF-8.
+    pub fn decode_to_string(&self, tokens: &[u32], vocabulary: &Vocabulary) -> Result<String> {
+        Ok(String::from_utf8(self.decode(tokens, vocabulary)?)?)
+    }
+
+    /// Encodes every input like [`encode`](BPE::encode) does, keeping their order.
+    ///
+    /// Like [`encode`](BPE::encode), this returns the plain ids without truncation or padding.
+    /// [`encode_batch_with_offsets`](BPE::encode_batch_with_offsets) produces model-ready
+    /// batches of equal length with attention masks instead.
+    ///
+    /// With the `parallel` feature the inputs are spread over the rayon thread pool. The
+    /// vocabulary is only read, so all threads share it without locking.
+    ///
+    /// ```rust
+    /// use rust_bpe::BPE;
+    ///
+    /// let bpe = BPE::new();
+    /// let vocabulary = bpe.build("abababcab");
+    /// let batch = bpe.encode_batch(&["abc", "cab"], &vocabulary);
+    /// assert_eq!(batch, [vec![256, 99], vec![99, 256]]);
+    /// ```
+    pub fn encode_batch<S>(&self, inputs: &[S], vocabulary: &Vocabulary) -> Vec<Vec<u32>>
+    where
+        S: AsRef<[u8]> + Sync,
+    {
+        map_batch(inputs, |data| self.encode(data, vocabulary))
+    }
+
+    /// Decodes every token sequence like [`decode`](BPE::decode) does, keeping their order.
+    ///
+    /// Fails with the first unknown token in input order. With the `parallel` feature the
+    /// sequences are spread over the rayon thread pool.
+    pub fn decode_batch<T>(&self, sequences: &[T], vocabulary: &Vocabulary) -> Result<Vec<Vec<u8>>>
+    where
+        T: AsRef<[u32]> + Sync,
+    {
+        map_batch(sequences, |tokens| self.decode(tokens.as_ref(), vocabulary))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Applies `f` to every item in order.
+#[cfg(not(feature = "parallel"))]
+fn map_batch<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
+/// Applies `f` to every item on the rayon thread pool, keeping the order of the results.
+#[cfg(feature = "parallel")]
+fn map_batch<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R> {
+    use rayon::prelude::*;
+
+    items.par_iter().map(f).collect()
+}
+
+impl Default for BPE {
+    fn default() -> Self {
+        BPE::new()
+    }
+}
@@ -1,32 +1,672 @@
-use std::{fs::File, io::{BufReader, Read}};
+//! The `rust_bpe` command-line tool.
+//!
+//! Reads from standard input and writes to standard output unless paths are given, so it can
+//! be used in pipelines. Errors are reported on standard error with exit code 1, invalid
+//! arguments exit with code 2.
 
-use rust_bpe::BPE;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-fn main() {
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rust_bpe::{
+    decompress, AllowedSpecial, BpeTrainer, Compressor, EntropyCoding, GageCompressor, Normalizer,
+    RegexPreTokenizer, Vocabulary, Whitespace, BPE,
+};
+use serde_json::{json, Value};
 
-    // Make new BPE object.
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Train byte pair encoding vocabularies and tokenize text with them"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Learn a vocabulary from text files or standard input
+    Train(TrainArgs),
+    /// Encode text into token ids
+    Encode(EncodeArgs),
+    /// Decode token ids back into text
+    Decode(DecodeArgs),
+    /// Show a summary of a vocabulary
+    Inspect(InspectArgs),
+    /// Convert a vocabulary to another file format
+    Convert(ConvertArgs),
+    /// Compress any file with merges learned on the file itself
+    Compress(CompressArgs),
+    /// Restore a file written by `compress`, with either method
+    Decompress(DecompressArgs),
+}
+
+#[derive(Args)]
+struct TrainArgs {
+    /// Training files, standard input if none are given
+    files: Vec<PathBuf>,
+    /// Target number of tokens, including the 256 byte tokens and the special tokens
+    #[arg(long, default_value_t = 30_000)]
+    vocab_size: usize,
+    /// Pairs occurring less often are never merged
+    #[arg(long, default_value_t = 2)]
+    min_frequency: u64,
+    /// Upper bound on the number of merges
+    #[arg(long)]
+    max_merges: Option<usize>,
+    /// Special token reserved after the merges, can be repeated
+    #[arg(long = "special-token", value_name = "TOKEN")]
+    special_tokens: Vec<String>,
+    /// How the input is split into words before merging
+    #[arg(long, value_enum, default_value_t = PreTokenizerKind::Gpt2)]
+    pre_tokenizer: PreTokenizerKind,
+    /// Normalization applied before splitting, can be repeated to apply several in order
+    #[arg(long = "normalizer", value_enum, value_name = "NORMALIZER")]
+    normalizers: Vec<NormalizerKind>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Format of the written vocabulary
+    #[arg(long, value_enum, default_value_t = VocabFormat::Json)]
+    format: VocabFormat,
+    /// File for the merges of the `gpt2` format, `--output` receives the `vocab.json`
+    #[arg(long)]
+    output_merges: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct VocabArgs {
+    /// Vocabulary file
+    #[arg(short, long)]
+    vocab: PathBuf,
+    /// Format of the vocabulary file, detected from its contents if missing
+    #[arg(long, value_enum)]
+    vocab_format: Option<VocabFormat>,
+    /// `merges.txt` of a GPT-2 vocabulary, whose `vocab.json` is given with `--vocab`
+    #[arg(long)]
+    merges: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct EncodeArgs {
+    #[command(flatten)]
+    vocab: VocabArgs,
+    /// Input file, standard input if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// How the token ids are written
+    #[arg(long, value_enum, default_value_t = IdFormat::Text)]
+    format: IdFormat,
+    /// Encode every line on its own and write the ids of one line per line
+    #[arg(long)]
+    lines: bool,
+    /// Encode special tokens in the input as their ids instead of plain text
+    #[arg(long)]
+    allow_special: bool,
+    /// Do not add the special tokens of the vocabulary's post-processor
+    #[arg(long)]
+    no_post_process: bool,
+}
+
+#[derive(Args)]
+struct DecodeArgs {
+    #[command(flatten)]
+    vocab: VocabArgs,
+    /// Input file, standard input if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// How the token ids are read
+    #[arg(long, value_enum, default_value_t = IdFormat::Text)]
+    format: IdFormat,
+    /// Decode every line of ids on its own and write one line of text each
+    #[arg(long)]
+    lines: bool,
+    /// Leave special tokens out of the decoded text
+    #[arg(long)]
+    skip_special_tokens: bool,
+}
+
+#[derive(Args)]
+struct InspectArgs {
+    #[command(flatten)]
+    vocab: VocabArgs,
+    /// How the summary is written
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Text)]
+    format: SummaryFormat,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Vocabulary file to convert
+    input: PathBuf,
+    /// Format of the input, detected from its contents if missing
+    #[arg(long, value_enum)]
+    from: Option<VocabFormat>,
+    /// `merges.txt` of a GPT-2 vocabulary, whose `vocab.json` is the input
+    #[arg(long)]
+    merges: Option<PathBuf>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Format of the written vocabulary
+    #[arg(long, value_enum)]
+    format: VocabFormat,
+    /// File for the merges of the `gpt2` format, `--output` receives the `vocab.json`
+    #[arg(long)]
+    output_merges: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct CompressArgs {
+    /// Input file, standard input if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Compression algorithm
+    #[arg(long, value_enum, default_value_t = Method::Bpe)]
+    method: Method,
+    /// Maximum number of tokens of the `bpe` method, including the 256 byte tokens
+    #[arg(long, default_value_t = 4096)]
+    vocab_size: usize,
+    /// Coding of the token stream of the `bpe` method
+    #[arg(long, value_enum, default_value_t = Entropy::Huffman)]
+    entropy: Entropy,
+    /// Maximum block size in bytes of the `gage` method
+    #[arg(long, default_value_t = 5000, value_parser = clap::value_parser!(u64).range(1..))]
+    block_size: u64,
+    /// Pairs occurring less often are never merged [default: 4 for `bpe`, 3 for `gage`]
+    #[arg(long)]
+    min_frequency: Option<u32>,
+    /// Do not report the compression ratio on standard error
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Args)]
+struct DecompressArgs {
+    /// Input file, standard input if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, standard output if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Method {
+    /// Token ids of merges learned on the whole input, see `rust_bpe::Compressor`
+    Bpe,
+    /// Gage's algorithm, pairs replaced by unused byte values block by block
+    Gage,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Entropy {
+    /// Indices into the table of used tokens as varints
+    None,
+    /// Canonical Huffman codes from the token frequencies
+    Huffman,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum VocabFormat {
+    /// The versioned JSON schema of this crate
+    Json,
+    /// Hugging Face `tokenizer.json`
+    Hf,
+    /// tiktoken rank file
+    Tiktoken,
+    /// GPT-2 `vocab.json` with a separate `merges.txt`
+    Gpt2,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IdFormat {
+    /// Ids separated by spaces
+    Text,
+    /// A JSON array of ids, or an array of arrays with `--lines`
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SummaryFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PreTokenizerKind {
+    /// Merge the input as a whole
+    None,
+    /// Split between whitespace and other characters
+    Whitespace,
+    /// The GPT-2 regex
+    Gpt2,
+    /// The cl100k regex
+    Cl100k,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum NormalizerKind {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+    Lowercase,
+    StripAccents,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Train(args) => train(args),
+        Command::Encode(args) => encode(args),
+        Command::Decode(args) => decode(args),
+        Command::Inspect(args) => inspect(args),
+        Command::Convert(args) => convert(args),
+        Command::Compress(args) => compress(args),
+        Command::Decompress(args) => decompress_file(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // A closed pipe, as in `rust_bpe encode | head`, is not an error of ours.
+        Err(err) if is_broken_pipe(err.as_ref()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rust_bpe: error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn train(args: TrainArgs) -> CliResult<()> {
+    let mut trainer = BpeTrainer::new()
+        .vocab_size(args.vocab_size)
+        .min_frequency(args.min_frequency)
+        .special_tokens(args.special_tokens);
+    if let Some(max_merges) = args.max_merges {
+        trainer = trainer.max_merges(max_merges);
+    }
+    let mut bpe = BPE::with_trainer(trainer);
+    match args.pre_tokenizer {
+        PreTokenizerKind::None => {}
+        PreTokenizerKind::Whitespace => bpe.set_pre_tokenizer(Whitespace),
+        PreTokenizerKind::Gpt2 => bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2()),
+        PreTokenizerKind::Cl100k => bpe.set_pre_tokenizer(RegexPreTokenizer::cl100k()),
+    }
+    let mut normalizers: Vec<_> = args
+        .normalizers
+        .iter()
+        .map(|&kind| normalizer(kind))
+        .collect();
+    match normalizers.len() {
+        0 => {}
+        1 => bpe.set_normalizer(normalizers.remove(0)),
+        _ => bpe.set_normalizer(Normalizer::Sequence(normalizers)),
+    }
+
+    let vocabulary = match args.files.as_slice() {
+        [] => bpe.build_from_reader(io::stdin().lock())?,
+        [file] if is_stdio(file) => bpe.build_from_reader(io::stdin().lock())?,
+        files => bpe.build_from_files(files)?,
+    };
+    eprintln!(
+        "learned {} merges, {} tokens",
+        vocabulary.merges().len(),
+        vocabulary.len()
+    );
+    write_vocabulary(
+        &vocabulary,
+        args.output.as_deref(),
+        args.format,
+        args.output_merges.as_deref(),
+    )
+}
+
+fn encode(args: EncodeArgs) -> CliResult<()> {
+    let vocabulary = load_vocabulary(
+        &args.vocab.vocab,
+        args.vocab.vocab_format,
+        args.vocab.merges.as_deref(),
+    )?;
+    let mut bpe = BPE::new();
+    if args.allow_special {
+        bpe.set_allowed_special(AllowedSpecial::All);
+    }
+    bpe.set_add_special_tokens(!args.no_post_process);
+    let input = read_input(args.input.as_deref())?;
+
+    let mut output = create_output(args.output.as_deref())?;
+    if args.lines {
+        let lines = split_lines(&input);
+        let batch = bpe.encode_batch(&lines, &vocabulary);
+        match args.format {
+            IdFormat::Text => {
+                for ids in &batch {
+                    writeln!(output, "{}", join_ids(ids))?;
+                }
+            }
+            IdFormat::Json => writeln!(output, "{}", json!(batch))?,
+        }
+    } else {
+        let ids = bpe.encode(&input, &vocabulary);
+        match args.format {
+            IdFormat::Text => writeln!(output, "{}", join_ids(&ids))?,
+            IdFormat::Json => writeln!(output, "{}", json!(ids))?,
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn decode(args: DecodeArgs) -> CliResult<()> {
+    let vocabulary = load_vocabulary(
+        &args.vocab.vocab,
+        args.vocab.vocab_format,
+        args.vocab.merges.as_deref(),
+    )?;
     let mut bpe = BPE::new();
+    bpe.set_skip_special_tokens(args.skip_special_tokens);
+    let input = read_input(args.input.as_deref())?;
+    let input = String::from_utf8(input).map_err(|_| "token ids are not valid UTF-8")?;
 
-    // Read training file for vocabulary.
-    let file_path = "../input.txt";
-    let learn_file = File::open(file_path).unwrap();
-    let mut buf_reader = BufReader::new(learn_file);
-    let mut data = String::new();
-    buf_reader.read_to_string(&mut data).unwrap();
+    let batch: Vec<Vec<u32>> = match (args.format, args.lines) {
+        (IdFormat::Text, false) => vec![parse_ids(&input)?],
+        (IdFormat::Text, true) => input.lines().map(parse_ids).collect::<CliResult<_>>()?,
+        (IdFormat::Json, false) => vec![serde_json::from_str(&input)?],
+        (IdFormat::Json, true) => serde_json::from_str(&input)?,
+    };
+    let decoded = bpe.decode_batch(&batch, &vocabulary)?;
 
-    // Build vocabulary.
-    let vocabulary = bpe.build(&data);
-    // Optional: 
+    let mut output = create_output(args.output.as_deref())?;
+    for bytes in decoded {
+        output.write_all(&bytes)?;
+        if args.lines {
+            output.write_all(b"\n")?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn inspect(args: InspectArgs) -> CliResult<()> {
+    let vocabulary = load_vocabulary(
+        &args.vocab.vocab,
+        args.vocab.vocab_format,
+        args.vocab.merges.as_deref(),
+    )?;
+    let special_tokens: Vec<(String, u32)> = vocabulary
+        .special_tokens()
+        .iter()
+        .map(|&id| {
+            let token = vocabulary.token(id).unwrap_or_default();
+            (String::from_utf8_lossy(token).into_owned(), id)
+        })
+        .collect();
+    let normalizer = vocabulary.normalizer().map(Normalizer::to_json);
+    let pre_tokenizer = vocabulary.pre_tokenizer().map(|pre_tokenizer| {
+        pre_tokenizer
+            .to_json()
+            .unwrap_or_else(|| json!(format!("{pre_tokenizer:?}")))
+    });
+    let merge_mode = format!("{:?}", vocabulary.merge_mode()).to_lowercase();
+    let post_processor = vocabulary
+        .post_processor()
+        .map(|p| p.template().to_string());
 
-    // Read file that needs to be encoded
-    let file_path = "../input.txt";
-    let input_file = File::open(file_path).unwrap();
-    let mut buf_reader = BufReader::new(input_file);
-    let mut data = String::new();
-    buf_reader.read_to_string(&mut data).unwrap();
+    let mut output = io::stdout().lock();
+    match args.format {
+        SummaryFormat::Json => {
+            let special_tokens: serde_json::Map<_, _> = special_tokens
+                .iter()
+                .map(|(token, id)| (token.clone(), json!(id)))
+                .collect();
+            let summary = json!({
+                "tokens": vocabulary.len(),
+                "merges": vocabulary.merges().len(),
+                "special_tokens": special_tokens,
+                "merge_mode": merge_mode,
+                "normalizer": normalizer,
+                "pre_tokenizer": pre_tokenizer,
+                "add_prefix_space": vocabulary.add_prefix_space(),
+                "post_processor": post_processor,
+            });
+            writeln!(output, "{}", serde_json::to_string_pretty(&summary)?)?;
+        }
+        SummaryFormat::Text => {
+            let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".into());
+            writeln!(output, "tokens:           {}", vocabulary.len())?;
+            writeln!(output, "merges:           {}", vocabulary.merges().len())?;
+            let special: Vec<_> = special_tokens
+                .iter()
+                .map(|(token, id)| format!("{token} ({id})"))
+                .collect();
+            writeln!(
+                output,
+                "special tokens:   {}",
+                or_none(Some(special.join(", ")).filter(|s| !s.is_empty()))
+            )?;
+            writeln!(output, "merge mode:       {merge_mode}")?;
+            writeln!(
+                output,
+                "normalizer:       {}",
+                or_none(normalizer.map(|n| n.to_string()))
+            )?;
+            writeln!(
+                output,
+                "pre-tokenizer:    {}",
+                or_none(pre_tokenizer.map(|p| p.to_string()))
+            )?;
+            writeln!(
+                output,
+                "add prefix space: {}",
+                vocabulary.add_prefix_space()
+            )?;
+            writeln!(output, "post-processor:   {}", or_none(post_processor))?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn convert(args: ConvertArgs) -> CliResult<()> {
+    let vocabulary = load_vocabulary(&args.input, args.from, args.merges.as_deref())?;
+    write_vocabulary(
+        &vocabulary,
+        args.output.as_deref(),
+        args.format,
+        args.output_merges.as_deref(),
+    )
+}
+
+fn compress(args: CompressArgs) -> CliResult<()> {
+    let input = read_input(args.input.as_deref())?;
+    let compressed = match args.method {
+        Method::Bpe => {
+            let entropy_coding = match args.entropy {
+                Entropy::None => EntropyCoding::None,
+                Entropy::Huffman => EntropyCoding::Huffman,
+            };
+            let mut compressor = Compressor::new()
+                .vocab_size(args.vocab_size)
+                .entropy_coding(entropy_coding);
+            if let Some(min_frequency) = args.min_frequency {
+                compressor = compressor.min_frequency(min_frequency.into());
+            }
+            compressor.compress(&input)
+        }
+        Method::Gage => {
+            let block_size = usize::try_from(args.block_size).unwrap_or(usize::MAX);
+            let mut compressor = GageCompressor::new().block_size(block_size);
+            if let Some(min_frequency) = args.min_frequency {
+                compressor = compressor.min_frequency(min_frequency);
+            }
+            compressor.compress(&input)
+        }
+    };
+    if !args.quiet {
+        eprintln!(
+            "{} -> {} bytes, ratio {:.3}, {} merges",
+            compressed.input_len(),
+            compressed.compressed_len(),
+            compressed.ratio(),
+            compressed.merges()
+        );
+    }
+    let mut output = create_output(args.output.as_deref())?;
+    output.write_all(compressed.as_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+fn decompress_file(args: DecompressArgs) -> CliResult<()> {
+    let input = read_input(args.input.as_deref())?;
+    let data = decompress(&input)?;
+    let mut output = create_output(args.output.as_deref())?;
+    output.write_all(&data)?;
+    output.flush()?;
+    Ok(())
+}
 
-    // Encode the data
-    let tokens = bpe.encode(&data, );
+fn normalizer(kind: NormalizerKind) -> Normalizer {
+    match kind {
+        NormalizerKind::Nfc => Normalizer::Nfc,
+        NormalizerKind::Nfd => Normalizer::Nfd,
+        NormalizerKind::Nfkc => Normalizer::Nfkc,
+        NormalizerKind::Nfkd => Normalizer::Nfkd,
+        NormalizerKind::Lowercase => Normalizer::Lowercase,
+        NormalizerKind::StripAccents => Normalizer::StripAccents,
+    }
+}
+
+/// Reads a vocabulary in `format`. Without a format, a vocabulary with `merges` is a GPT-2
+/// vocabulary, `.tiktoken` files are rank files, JSON files with a `model` are Hugging Face
+/// tokenizers and other JSON files use this crate's schema.
+fn load_vocabulary(
+    path: &Path,
+    format: Option<VocabFormat>,
+    merges: Option<&Path>,
+) -> CliResult<Vocabulary> {
+    let with_path = |err: rust_bpe::Error| format!("{}: {err}", path.display());
+    let format = format.or(merges.map(|_| VocabFormat::Gpt2));
+    let vocabulary = match format {
+        Some(VocabFormat::Json) => Vocabulary::load(path),
+        Some(VocabFormat::Gpt2) => {
+            let merges = merges.ok_or("the gpt2 format needs its merges.txt, see `--merges`")?;
+            Vocabulary::from_gpt2_files(path, merges)
+        }
+        Some(VocabFormat::Hf) => Vocabulary::load_hf_tokenizer(path),
+        Some(VocabFormat::Tiktoken) => Vocabulary::from_tiktoken_file(path),
+        None if path.extension().is_some_and(|ext| ext == "tiktoken") => {
+            Vocabulary::from_tiktoken_file(path)
+        }
+        None => {
+            let contents = fs::read(path).map_err(|err| with_path(err.into()))?;
+            match serde_json::from_slice::<Value>(&contents) {
+                Ok(value) if value.get("model").is_some() => {
+                    Vocabulary::from_hf_tokenizer_json(&value)
+                }
+                Ok(value) => Vocabulary::from_json(&value),
+                Err(_) => Vocabulary::from_tiktoken_file(path),
+            }
+        }
+    };
+    Ok(vocabulary.map_err(with_path)?)
+}
+
+/// Writes `vocabulary` in `format` to `path`, and its merges to `merges` for the `gpt2` format.
+fn write_vocabulary(
+    vocabulary: &Vocabulary,
+    path: Option<&Path>,
+    format: VocabFormat,
+    merges: Option<&Path>,
+) -> CliResult<()> {
+    if let VocabFormat::Gpt2 = format {
+        let (Some(path), Some(merges)) = (path.filter(|path| !is_stdio(path)), merges) else {
+            return Err("the gpt2 format needs `--output` and `--output-merges` files".into());
+        };
+        vocabulary.save_gpt2_files(path, merges)?;
+        return Ok(());
+    }
+    let mut output = create_output(path)?;
+    match format {
+        VocabFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, &vocabulary.to_json()?)?;
+            writeln!(output)?;
+        }
+        VocabFormat::Hf => {
+            serde_json::to_writer_pretty(&mut output, &vocabulary.to_hf_tokenizer_json())?;
+            writeln!(output)?;
+        }
+        VocabFormat::Tiktoken => vocabulary.write_tiktoken(&mut output)?,
+        VocabFormat::Gpt2 => unreachable!("written to files above"),
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+fn read_input(path: Option<&Path>) -> CliResult<Vec<u8>> {
+    let mut input = Vec::new();
+    match path.filter(|path| !is_stdio(path)) {
+        Some(path) => {
+            input = fs::read(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        }
+        None => {
+            io::stdin().lock().read_to_end(&mut input)?;
+        }
+    }
+    Ok(input)
+}
+
+fn create_output(path: Option<&Path>) -> CliResult<Box<dyn Write>> {
+    Ok(match path.filter(|path| !is_stdio(path)) {
+        Some(path) => {
+            let file = File::create(path).map_err(|err| format!("{}: {err}", path.display()))?;
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+/// The lines of `input` without their line breaks.
+fn split_lines(input: &[u8]) -> Vec<&[u8]> {
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    if input.is_empty() {
+        return Vec::new();
+    }
+    input.split(|&b| b == b'\n').collect()
+}
+
+fn join_ids(ids: &[u32]) -> String {
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join(" ")
+}
+
+fn parse_ids(text: &str) -> CliResult<Vec<u32>> {
+    text.split_whitespace()
+        .map(|id| {
+            id.parse()
+                .map_err(|_| format!("invalid token id {id:?}").into())
+        })
+        .collect()
+}
 
-    let decoded = bpe.decode(&tokens);
+fn is_broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    let io_error = match err.downcast_ref::<rust_bpe::Error>() {
+        Some(rust_bpe::Error::Io(err)) => Some(err),
+        _ => err.downcast_ref::<io::Error>(),
+    };
+    io_error.is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
 }
@@ -0,0 +1,253 @@
+//! Normalizing text before it is pre-tokenized.
+//!
+//! The same text can be spelled with different code points, for example `é` as the single code
+//! point U+00E9 or as `e` followed by a combining acute accent. A normalizer maps such spellings
+//! to one form, so they are learned as the same tokens. The JSON form of a normalizer is the one
+//! used by Hugging Face `tokenizer.json` files.
+
+use std::ops::Range;
+
+use serde_json::{json, Value};
+use unicode_categories::UnicodeCategories;
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{
+    is_nfc_quick, is_nfd_quick, is_nfkc_quick, is_nfkd_quick, IsNormalized, UnicodeNormalization,
+};
+
+use crate::error::{Error, Result};
+
+/// Bytes of text that [`Normalizer::normalize_aligned`] joins into one group at most, longer
+/// groups map to the rest of their segment as a whole.
+const MAX_GROUP: usize = 256;
+
+/// A text normalization applied before pre-tokenization.
+///
+/// ```rust
+/// use rust_bpe::Normalizer;
+///
+/// let normalizer = Normalizer::Sequence(vec![
+///     Normalizer::Nfd,
+///     Normalizer::StripAccents,
+///     Normalizer::Lowercase,
+/// ]);
+/// assert_eq!(normalizer.normalize("Crème Brûlée"), "creme brulee");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Normalizer {
+    /// Canonical composition.
+    Nfc,
+    /// Canonical decomposition.
+    Nfd,
+    /// Compatibility composition, which also maps variants like `ﬁ` or `²` to plain characters.
+    Nfkc,
+    /// Compatibility decomposition.
+    Nfkd,
+    /// Unicode lowercasing.
+    Lowercase,
+    /// Removes nonspacing marks. Accents are only separate marks in decomposed text, so this is
+    /// usually preceded by [`Nfd`](Normalizer::Nfd) or [`Nfkd`](Normalizer::Nfkd).
+    StripAccents,
+    /// Applies the normalizers in order.
+    Sequence(Vec<Normalizer>),
+}
+
+impl Normalizer {
+    /// Normalizes `text`.
+    pub fn normalize(&self, text: &str) -> String {
+        match self {
+            Normalizer::Nfc => text.nfc().collect(),
+            Normalizer::Nfd => text.nfd().collect(),
+            Normalizer::Nfkc => text.nfkc().collect(),
+            Normalizer::Nfkd => text.nfkd().collect(),
+            Normalizer::Lowercase => text.to_lowercase(),
+            Normalizer::StripAccents => text.chars().filter(|c| !c.is_mark_nonspacing()).collect(),
+            Normalizer::Sequence(normalizers) => normalizers
+                .iter()
+                .fold(text.to_string(), |text, normalizer| {
+                    normalizer.normalize(&text)
+                }),
+        }
+    }
+
+    /// Normalizes the valid UTF-8 parts of `bytes`, invalid sequences are kept as they are.
+    pub(crate) fn normalize_bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut normalized = Vec::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            normalized.extend_from_slice(self.normalize(chunk.valid()).as_bytes());
+            normalized.extend_from_slice(chunk.invalid());
+        }
+        normalized
+    }
+
+    /// Normalizes `bytes` like [`normalize_bytes`](Normalizer::normalize_bytes) and maps every
+    /// normalized byte to the range of `bytes` it was produced from.
+    ///
+    /// The text is first cut into segments at stable boundaries, before characters that nothing
+    /// in front of them can interact with, see [`stable_before`](Normalizer::stable_before).
+    /// Segments are normalized and mapped on their own, so the work stays linear in the input.
+    /// Within a segment the text is cut before every starter character and the cuts are
+    /// normalized on their own. Neighbouring cuts are joined while that changes the result, as
+    /// it does for a character composed from both or a final sigma. Once joined cuts grow past
+    /// [`MAX_GROUP`] bytes, or the cuts of a segment do not add up to its normalized form, the
+    /// rest of the segment maps to all of its bytes. Should the segments still not add up to the
+    /// normalized whole, every byte maps to all of `bytes`.
+    pub(crate) fn normalize_aligned(&self, bytes: &[u8]) -> (Vec<u8>, Vec<Range<usize>>) {
+        let expected = self.normalize_bytes(bytes);
+        let mut normalized = Vec::with_capacity(expected.len());
+        let mut alignments = Vec::with_capacity(expected.len());
+
+        let mut offset = 0;
+        for chunk in bytes.utf8_chunks() {
+            let text = chunk.valid();
+            let boundaries = text
+                .char_indices()
+                .filter(|&(i, c)| i > 0 && self.stable_before(c))
+                .map(|(i, _)| i)
+                .chain((!text.is_empty()).then_some(text.len()));
+            let mut start = 0;
+            for end in boundaries {
+                let (part, ranges) = self.align_segment(&text[start..end], offset + start);
+                normalized.extend_from_slice(part.as_bytes());
+                alignments.extend(ranges);
+                start = end;
+            }
+            offset += text.len();
+            for _ in chunk.invalid() {
+                normalized.push(bytes[offset]);
+                alignments.push(offset..offset + 1);
+                offset += 1;
+            }
+        }
+
+        if normalized != expected {
+            let len = expected.len();
+            return (expected, vec![0..bytes.len(); len]);
+        }
+        (normalized, alignments)
+    }
+
+    /// Normalizes one segment of [`normalize_aligned`](Normalizer::normalize_aligned) that
+    /// starts at `offset` of the input.
+    fn align_segment(&self, segment: &str, offset: usize) -> (String, Vec<Range<usize>>) {
+        let mut normalized = String::new();
+        let mut alignments = Vec::new();
+        let mut push = |part: &str, range: Range<usize>| {
+            normalized.push_str(part);
+            alignments.extend(std::iter::repeat_n(range, part.len()));
+        };
+
+        let cuts = segment
+            .char_indices()
+            .filter(|&(i, c)| i > 0 && canonical_combining_class(c) == 0)
+            .map(|(i, _)| i)
+            .chain(std::iter::once(segment.len()));
+        // The joined cuts not pushed yet, as the start in `segment` and their normalized form.
+        let mut pending: Option<(usize, String)> = None;
+        let mut previous = 0;
+        let mut start = 0;
+        for end in cuts {
+            let Some((group, group_normalized)) = pending else {
+                pending = Some((start, self.normalize(&segment[start..end])));
+                start = end;
+                continue;
+            };
+            if group < previous && end - group > MAX_GROUP {
+                pending = Some((group, self.normalize(&segment[group..])));
+                break;
+            }
+            let part = self.normalize(&segment[start..end]);
+            let joined = self.normalize(&segment[group..end]);
+            pending = if joined == group_normalized.clone() + &part {
+                push(&group_normalized, offset + group..offset + start);
+                Some((start, part))
+            } else {
+                Some((group, joined))
+            };
+            previous = start;
+            start = end;
+        }
+        if let Some((group, group_normalized)) = pending {
+            push(&group_normalized, offset + group..offset + segment.len());
+        }
+
+        let expected = self.normalize(segment);
+        if normalized != expected {
+            let len = expected.len();
+            return (expected, vec![offset..offset + segment.len(); len]);
+        }
+        (normalized, alignments)
+    }
+
+    /// Whether a cut before `c` never changes the normalized text, the part in front of it
+    /// normalizing the same whatever follows and the other way round.
+    ///
+    /// For the Unicode forms these are starters that nothing composes with from the left, the
+    /// boundaries of UAX #15. A final sigma looks past case-ignorable characters on both sides,
+    /// so lowercasing is only cut before white space. A sequence is cut before ASCII characters
+    /// every step can be cut before, as those stay the same character from step to step.
+    fn stable_before(&self, c: char) -> bool {
+        let starter = || canonical_combining_class(c) == 0;
+        let once = || std::iter::once(c);
+        match self {
+            Normalizer::Nfc => starter() && is_nfc_quick(once()) == IsNormalized::Yes,
+            Normalizer::Nfd => starter() && is_nfd_quick(once()) == IsNormalized::Yes,
+            Normalizer::Nfkc => starter() && is_nfkc_quick(once()) == IsNormalized::Yes,
+            Normalizer::Nfkd => starter() && is_nfkd_quick(once()) == IsNormalized::Yes,
+            Normalizer::Lowercase => c.is_whitespace(),
+            Normalizer::StripAccents => true,
+            Normalizer::Sequence(normalizers) => {
+                c.is_ascii()
+                    && normalizers
+                        .iter()
+                        .all(|normalizer| normalizer.stable_before(c))
+            }
+        }
+    }
+
+    /// The normalizer in the JSON form of `tokenizer.json`, e.g. `{"type": "NFC"}`.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Normalizer::Sequence(normalizers) => json!({
+                "type": "Sequence",
+                "normalizers": normalizers.iter().map(Normalizer::to_json).collect::<Vec<_>>(),
+            }),
+            _ => json!({ "type": self.name() }),
+        }
+    }
+
+    /// Parses the JSON form written by [`to_json`](Normalizer::to_json).
+    pub fn from_json(value: &Value) -> Result<Normalizer> {
+        let normalizer = match value["type"].as_str() {
+            Some("NFC") => Normalizer::Nfc,
+            Some("NFD") => Normalizer::Nfd,
+            Some("NFKC") => Normalizer::Nfkc,
+            Some("NFKD") => Normalizer::Nfkd,
+            Some("Lowercase") => Normalizer::Lowercase,
+            Some("StripAccents") => Normalizer::StripAccents,
+            Some("Sequence") => Normalizer::Sequence(
+                value["normalizers"]
+                    .as_array()
+                    .ok_or_else(|| {
+                        Error::InvalidVocabulary("normalizer sequence without normalizers".into())
+                    })?
+                    .iter()
+                    .map(Normalizer::from_json)
+                    .collect::<Result<_>>()?,
+            ),
+            _ => return Err(Error::Unsupported(format!("normalizer {}", value["type"]))),
+        };
+        Ok(normalizer)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Normalizer::Nfc => "NFC",
+            Normalizer::Nfd => "NFD",
+            Normalizer::Nfkc => "NFKC",
+            Normalizer::Nfkd => "NFKD",
+            Normalizer::Lowercase => "Lowercase",
+            Normalizer::StripAccents => "StripAccents",
+            Normalizer::Sequence(_) => "Sequence",
+        }
+    }
+}
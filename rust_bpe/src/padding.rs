@@ -0,0 +1,99 @@
+//! Padding encodings to a common length.
+
+use crate::encoding::Encoding;
+use crate::truncation::Side;
+use crate::vocabulary::Vocabulary;
+
+/// The length padded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingLength {
+    /// The length of the longest encoding in the batch.
+    #[default]
+    Longest,
+    /// A fixed number of tokens. Longer encodings are left as they are.
+    Fixed(usize),
+}
+
+/// Padding applied by [`BPE::encode_with_offsets`](crate::BPE::encode_with_offsets) and
+/// [`BPE::encode_batch_with_offsets`](crate::BPE::encode_batch_with_offsets).
+///
+/// ```rust
+/// use rust_bpe::{Padding, Side, BPE};
+///
+/// let mut bpe = BPE::new();
+/// let mut vocabulary = bpe.build("");
+/// let pad = vocabulary.add_special_token("<pad>");
+/// bpe.set_padding(Padding::new(pad).pad_to_multiple_of(4).side(Side::Left));
+///
+/// let batch = bpe.encode_batch_with_offsets(&["ab", "abcde"], &vocabulary);
+/// assert_eq!(batch[0].ids(), [pad, pad, pad, pad, pad, pad, 97, 98]);
+/// assert_eq!(batch[0].attention_mask(), [0, 0, 0, 0, 0, 0, 1, 1]);
+/// assert_eq!(batch[1].len(), 8);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Padding {
+    pad_id: u32,
+    length: PaddingLength,
+    pad_to_multiple_of: Option<usize>,
+    side: Side,
+}
+
+impl Padding {
+    /// Pads with the token `pad_id` on the right, to the longest encoding of a batch.
+    pub fn new(pad_id: u32) -> Padding {
+        Padding {
+            pad_id,
+            length: PaddingLength::Longest,
+            pad_to_multiple_of: None,
+            side: Side::Right,
+        }
+    }
+
+    /// The length padded to.
+    pub fn length(mut self, length: PaddingLength) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Rounds the padded length up to a multiple of `multiple`, which suits hardware that works
+    /// on fixed-size blocks. `0` and `1` leave the length as it is.
+    pub fn pad_to_multiple_of(mut self, multiple: usize) -> Self {
+        self.pad_to_multiple_of = Some(multiple).filter(|&multiple| multiple > 1);
+        self
+    }
+
+    /// The end that padding tokens are added to.
+    pub fn side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// The token padded with.
+    pub fn pad_id(&self) -> u32 {
+        self.pad_id
+    }
+
+    /// Pads every encoding of `batch` to the same length.
+    pub(crate) fn apply(&self, batch: &mut [Encoding], vocabulary: &Vocabulary) {
+        let longest = |encodings: &[Encoding]| {
+            encodings
+                .iter()
+                .flat_map(|encoding| std::iter::once(encoding).chain(encoding.overflowing()))
+                .map(Encoding::len)
+                .max()
+                .unwrap_or(0)
+        };
+        let mut length = match self.length {
+            PaddingLength::Longest => longest(batch),
+            PaddingLength::Fixed(length) => length,
+        };
+        if let Some(multiple) = self.pad_to_multiple_of {
+            length = length.div_ceil(multiple) * multiple;
+        }
+        let token = vocabulary.token(self.pad_id).unwrap_or_default();
+        let token = String::from_utf8_lossy(token);
+        for encoding in batch {
+            encoding.pad(length, self.pad_id, &token, self.side);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Adding special tokens around encoded inputs.
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
+use crate::vocabulary::Vocabulary;
+
+/// Wraps encoded inputs with special tokens, such as beginning and end of sequence markers or
+/// the separator between the two inputs of a pair.
+///
+/// The template lists the special tokens and the inputs `$A` and `$B`, separated by spaces. Any
+/// item can be followed by `:` and the type id of its tokens, which is `0` by default. Single
+/// inputs use the template up to `$B`, pairs use the whole template. A template without `$B`
+/// has `$B:1` appended for pairs. Templates where single inputs are wrapped differently, such as
+/// `"<s> $A"` with `"<s> $A <s> $B"`, are created with [`with_pair`](PostProcessor::with_pair).
+///
+/// The post-processor is stored in the [`Vocabulary`], which makes sure its special tokens
+/// exist. [`BPE::encode`](crate::BPE::encode) and the other encoding methods apply it, and
+/// [`BPE::set_skip_special_tokens`](crate::BPE::set_skip_special_tokens) drops the added tokens
+/// again when decoding.
+///
+/// ```rust
+/// use rust_bpe::{BpeTrainer, PostProcessor, BPE};
+///
+/// let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<s>", "</s>"]));
+/// let mut vocabulary = bpe.build("");
+/// let processor = PostProcessor::new("<s> $A </s> $B:1 </s>:1").unwrap();
+/// vocabulary.set_post_processor(processor).unwrap();
+/// let (start, end) = (256, 257);
+///
+/// assert_eq!(bpe.encode("a", &vocabulary), [start, 97, end]);
+/// let pair = bpe.encode_pair_with_offsets("a", "b", &vocabulary);
+/// assert_eq!(pair.ids(), [start, 97, end, 98, end]);
+/// assert_eq!(pair.type_ids(), [0, 0, 0, 1, 1]);
+///
+/// bpe.set_skip_special_tokens(true);
+/// assert_eq!(bpe.decode(pair.ids(), &vocabulary).unwrap(), b"ab");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostProcessor {
+    template: String,
+    /// The template for pairs if it was given separately.
+    pair_template: Option<String>,
+    single: Vec<Item>,
+    pair: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    /// The first input if `second` is false, the second one otherwise.
+    Sequence {
+        second: bool,
+        type_id: u32,
+    },
+    Special {
+        token: String,
+        type_id: u32,
+    },
+}
+
+impl PostProcessor {
+    /// Parses `template`, which must contain `$A` once and may contain `$B` once after it.
+    pub fn new(template: &str) -> Result<PostProcessor> {
+        let (mut pair, first, second) = parse(template)?;
+        let single = match (first, second) {
+            (None, _) => return invalid(format!("{template:?} has no $A")),
+            (Some(first), Some(second)) if second < first => {
+                return invalid(format!("{template:?} has $B before $A"))
+            }
+            (Some(_), Some(second)) => pair[..second].to_vec(),
+            (Some(_), None) => {
+                let single = pair.clone();
+                pair.push(Item::Sequence {
+                    second: true,
+                    type_id: 1,
+                });
+                single
+            }
+        };
+        Ok(PostProcessor {
+            template: template.to_string(),
+            pair_template: None,
+            single,
+            pair,
+        })
+    }
+
+    /// Parses separate templates for single inputs and for pairs. The `single` template must
+    /// contain `$A` once and no `$B`, the `pair` template both once, in any order.
+    ///
+    /// ```rust
+    /// use rust_bpe::PostProcessor;
+    ///
+    /// let processor = PostProcessor::with_pair("<s> $A", "<s> $A <s> $B:1").unwrap();
+    /// assert_eq!(processor.added_tokens(false), 1);
+    /// assert_eq!(processor.added_tokens(true), 2);
+    /// ```
+    pub fn with_pair(single: &str, pair: &str) -> Result<PostProcessor> {
+        let (single_items, first, second) = parse(single)?;
+        if first.is_none() || second.is_some() {
+            return invalid(format!("{single:?} must contain $A and no $B"));
+        }
+        let (pair_items, first, second) = parse(pair)?;
+        if first.is_none() || second.is_none() {
+            return invalid(format!("{pair:?} must contain $A and $B"));
+        }
+        Ok(PostProcessor {
+            template: single.to_string(),
+            pair_template: Some(pair.to_string()),
+            single: single_items,
+            pair: pair_items,
+        })
+    }
+
+    /// The template this post-processor was created from, the one for single inputs if the
+    /// pair template was given separately.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The template for pairs given to [`with_pair`](PostProcessor::with_pair).
+    pub fn pair_template(&self) -> Option<&str> {
+        self.pair_template.as_deref()
+    }
+
+    /// The special tokens of the templates, in order of appearance.
+    pub fn special_tokens(&self) -> impl Iterator<Item = &str> {
+        let mut tokens: Vec<&str> = Vec::new();
+        for item in self.single.iter().chain(&self.pair) {
+            if let Item::Special { token, .. } = item {
+                if !tokens.contains(&token.as_str()) {
+                    tokens.push(token);
+                }
+            }
+        }
+        tokens.into_iter()
+    }
+
+    /// Number of special tokens added to a single input or to a pair.
+    pub fn added_tokens(&self, is_pair: bool) -> usize {
+        let items = if is_pair { &self.pair } else { &self.single };
+        items
+            .iter()
+            .filter(|item| matches!(item, Item::Special { .. }))
+            .count()
+    }
+
+    /// Adds the special tokens of the single-input template to `ids`.
+    pub(crate) fn apply_ids(&self, ids: Vec<u32>, vocabulary: &Vocabulary) -> Vec<u32> {
+        let mut processed = Vec::with_capacity(ids.len() + self.single.len());
+        for item in &self.single {
+            match item {
+                Item::Sequence { .. } => processed.extend_from_slice(&ids),
+                Item::Special { token, .. } => processed.extend(vocabulary.special_token_id(token)),
+            }
+        }
+        processed
+    }
+
+    fn build(
+        &self,
+        first: &Encoding,
+        second: Option<&Encoding>,
+        vocabulary: &Vocabulary,
+    ) -> Encoding {
+        let items = if second.is_some() {
+            &self.pair
+        } else {
+            &self.single
+        };
+        let mut encoding = Encoding::default();
+        for item in items {
+            match item {
+                Item::Sequence {
+                    second: false,
+                    type_id,
+                } => encoding.extend(first, *type_id),
+                Item::Sequence {
+                    second: true,
+                    type_id,
+                } => {
+                    if let Some(second) = second {
+                        encoding.extend(second, *type_id);
+                    }
+                }
+                Item::Special { token, type_id } => {
+                    if let Some(id) = vocabulary.special_token_id(token) {
+                        encoding.push_special(id, token.clone(), *type_id);
+                    }
+                }
+            }
+        }
+        encoding
+    }
+
+    /// The `TemplateProcessing` form of Hugging Face `tokenizer.json` files.
+    pub(crate) fn to_hf_json(&self, vocabulary: &Vocabulary) -> Value {
+        let items = |items: &[Item]| -> Vec<Value> {
+            items
+                .iter()
+                .map(|item| match item {
+                    Item::Sequence { second, type_id } => json!({
+                        "Sequence": { "id": if *second { "B" } else { "A" }, "type_id": type_id }
+                    }),
+                    Item::Special { token, type_id } => {
+                        json!({ "SpecialToken": { "id": token, "type_id": type_id } })
+                    }
+                })
+                .collect()
+        };
+        let mut special_tokens = serde_json::Map::new();
+        for token in self.special_tokens() {
+            let ids: Vec<u32> = vocabulary.special_token_id(token).into_iter().collect();
+            special_tokens.insert(
+                token.to_string(),
+                json!({ "id": token, "ids": ids, "tokens": [token] }),
+            );
+        }
+        json!({
+            "type": "TemplateProcessing",
+            "single": items(&self.single),
+            "pair": items(&self.pair),
+            "special_tokens": special_tokens,
+        })
+    }
+
+    /// Reads a `TemplateProcessing` post-processor of a `tokenizer.json` file, also as part of a
+    /// `Sequence`. Other post-processors add no tokens this crate knows of and give `None`.
+    pub(crate) fn from_hf_json(value: &Value) -> Result<Option<PostProcessor>> {
+        match value["type"].as_str() {
+            Some("TemplateProcessing") => {}
+            Some("Sequence") => {
+                let processors = value["processors"].as_array().into_iter().flatten();
+                for processor in processors {
+                    if let Some(processor) = PostProcessor::from_hf_json(processor)? {
+                        return Ok(Some(processor));
+                    }
+                }
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        }
+        let template = |value: &Value| -> Result<String> {
+            let items = value.as_array().ok_or_else(|| {
+                Error::InvalidVocabulary("template items must be an array".into())
+            })?;
+            let items = items.iter().map(|item| {
+                let (name, item) = match (&item["Sequence"], &item["SpecialToken"]) {
+                    (Value::Null, special) => (special["id"].as_str()?.to_string(), special),
+                    (sequence, _) => (format!("${}", sequence["id"].as_str()?), sequence),
+                };
+                Some(format!("{name}:{}", item["type_id"].as_u64().unwrap_or(0)))
+            });
+            items
+                .collect::<Option<Vec<_>>>()
+                .map(|items| items.join(" "))
+                .ok_or_else(|| Error::InvalidVocabulary(format!("invalid template {value}")))
+        };
+        let (single, pair) = (template(&value["single"])?, template(&value["pair"])?);
+        // Keep the single template form when the pair template implies the single one.
+        match PostProcessor::new(&pair) {
+            Ok(processor) if processor.single == parse(&single)?.0 => Ok(Some(processor)),
+            _ => PostProcessor::with_pair(&single, &pair).map(Some),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        match &self.pair_template {
+            Some(pair) => json!({ "type": "Template", "template": self.template, "pair": pair }),
+            None => json!({ "type": "Template", "template": self.template }),
+        }
+    }
+
+    pub(crate) fn from_json(value: &Value) -> Result<PostProcessor> {
+        match (value["type"].as_str(), value["template"].as_str()) {
+            (Some("Template"), Some(template)) => match value["pair"].as_str() {
+                Some(pair) => PostProcessor::with_pair(template, pair),
+                None => PostProcessor::new(template),
+            },
+            (Some("Template"), None) => Err(Error::InvalidVocabulary(
+                "post-processor without template".into(),
+            )),
+            _ => Err(Error::Unsupported(format!(
+                "post-processor {}",
+                value["type"]
+            ))),
+        }
+    }
+}
+
+/// Parses the items of `template` and finds the positions of `$A` and `$B`.
+fn parse(template: &str) -> Result<(Vec<Item>, Option<usize>, Option<usize>)> {
+    let mut items = Vec::new();
+    for item in template.split_whitespace() {
+        let (name, type_id) = match item.rsplit_once(':') {
+            Some((name, type_id)) if !name.is_empty() => match type_id.parse() {
+                Ok(type_id) => (name, type_id),
+                Err(_) => (item, 0),
+            },
+            _ => (item, 0),
+        };
+        items.push(match name {
+            "$A" | "$B" => Item::Sequence {
+                second: name == "$B",
+                type_id,
+            },
+            _ if name.starts_with('$') => return invalid(format!("unknown input {name}")),
+            _ => Item::Special {
+                token: name.to_string(),
+                type_id,
+            },
+        });
+    }
+
+    let position = |second: bool| {
+        let mut positions = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| matches!(item, Item::Sequence { second: s, .. } if *s == second));
+        match (positions.next(), positions.next()) {
+            (_, Some(_)) => Err(()),
+            (found, None) => Ok(found.map(|(i, _)| i)),
+        }
+    };
+    let (Ok(first), Ok(second)) = (position(false), position(true)) else {
+        return invalid(format!("{template:?} repeats an input"));
+    };
+    Ok((items, first, second))
+}
+
+fn invalid<T>(message: String) -> Result<T> {
+    Err(Error::InvalidTemplate(message))
+}
+
+impl fmt::Display for PostProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// Combines `first` and the optional `second` input into one encoding with the template of
+/// `processor`, or by concatenating them if there is none.
+///
+/// Every combination of the inputs and their overflowing parts is processed, the first one
+/// becomes the encoding and the others its overflowing parts.
+pub(crate) fn process(
+    processor: Option<&PostProcessor>,
+    mut first: Encoding,
+    second: Option<Encoding>,
+    vocabulary: &Vocabulary,
+) -> Encoding {
+    let build = |first: &Encoding, second: Option<&Encoding>| match processor {
+        Some(processor) => processor.build(first, second, vocabulary),
+        None => {
+            let mut encoding = Encoding::default();
+            encoding.extend(first, 0);
+            if let Some(second) = second {
+                encoding.extend(second, 1);
+            }
+            encoding
+        }
+    };
+
+    let first_overflowing = first.take_overflowing();
+    let firsts: Vec<&Encoding> = std::iter::once(&first).chain(&first_overflowing).collect();
+    let (second, second_overflowing) = match second {
+        Some(mut second) => {
+            let overflowing = second.take_overflowing();
+            (Some(second), overflowing)
+        }
+        None => (None, Vec::new()),
+    };
+    let seconds: Vec<Option<&Encoding>> = std::iter::once(second.as_ref())
+        .chain(second_overflowing.iter().map(Some))
+        .collect();
+
+    let mut combined = firsts
+        .iter()
+        .flat_map(|&first| seconds.iter().map(move |&second| build(first, second)));
+    let mut encoding = combined.next().unwrap_or_default();
+    encoding.set_overflowing(combined.collect());
+    encoding
+}
@@ -0,0 +1,202 @@
+//! Splitting input into pieces that are merged independently.
+//!
+//! Merges never cross the boundary between two pieces, so a pre-tokenizer decides which parts of
+//! the input can end up in the same token. [`BPE::build`](crate::BPE::build) splits the training
+//! data with the pre-tokenizer of the [`BPE`](crate::BPE) and records it in the vocabulary, which
+//! [`BPE::encode`](crate::BPE::encode) then uses to split its input the same way.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use fancy_regex::Regex;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+/// The pre-tokenization regex of GPT-2, also used by tiktoken's `r50k_base` and `p50k_base`.
+pub const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// The pre-tokenization regex of tiktoken's `cl100k_base`.
+pub const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Splits text into the pieces that are merged independently.
+pub trait PreTokenizer: fmt::Debug + Send + Sync {
+    /// Byte ranges of the pieces of `text`, in order and without overlap.
+    ///
+    /// Ranges must start and end on character boundaries. Text that is not covered by any range
+    /// becomes a piece of its own, so nothing is ever lost.
+    fn pre_tokenize(&self, text: &str) -> Vec<Range<usize>>;
+
+    /// Description stored by [`Vocabulary::save`](crate::Vocabulary::save), `None` if the
+    /// pre-tokenizer cannot be restored from a file.
+    fn to_json(&self) -> Option<Value> {
+        None
+    }
+}
+
+/// Splits text into runs of whitespace and runs of everything else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Whitespace;
+
+impl PreTokenizer for Whitespace {
+    fn pre_tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        let mut chars = text.char_indices().peekable();
+        while let Some((_, c)) = chars.next() {
+            match chars.peek() {
+                Some(&(i, next)) if next.is_whitespace() != c.is_whitespace() => {
+                    pieces.push(start..i);
+                    start = i;
+                }
+                Some(_) => {}
+                None => pieces.push(start..text.len()),
+            }
+        }
+        pieces
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        Some(json!({ "type": "Whitespace" }))
+    }
+}
+
+/// Splits text into the matches of a regular expression.
+///
+/// Patterns may use look-around, like the GPT-2 pattern does to keep the last space of a
+/// whitespace run for the following word.
+#[derive(Debug, Clone)]
+pub struct RegexPreTokenizer {
+    regex: Regex,
+}
+
+impl RegexPreTokenizer {
+    /// Pre-tokenizer for a custom `pattern`.
+    pub fn new(pattern: &str) -> Result<RegexPreTokenizer> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| Error::InvalidPattern(format!("{pattern:?}: {err}")))?;
+        Ok(RegexPreTokenizer { regex })
+    }
+
+    /// Pre-tokenizer using [`GPT2_PATTERN`].
+    pub fn gpt2() -> RegexPreTokenizer {
+        static GPT2: OnceLock<RegexPreTokenizer> = OnceLock::new();
+        GPT2.get_or_init(|| RegexPreTokenizer::new(GPT2_PATTERN).unwrap())
+            .clone()
+    }
+
+    /// Pre-tokenizer using [`CL100K_PATTERN`].
+    pub fn cl100k() -> RegexPreTokenizer {
+        static CL100K: OnceLock<RegexPreTokenizer> = OnceLock::new();
+        CL100K
+            .get_or_init(|| RegexPreTokenizer::new(CL100K_PATTERN).unwrap())
+            .clone()
+    }
+
+    /// The regular expression text.
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+impl PreTokenizer for RegexPreTokenizer {
+    fn pre_tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        // A match that fails with a backtracking limit ends the matching, the rest of the text
+        // then becomes a single piece.
+        self.regex
+            .find_iter(text)
+            .map_while(|found| found.ok())
+            .map(|found| found.range())
+            .collect()
+    }
+
+    fn to_json(&self) -> Option<Value> {
+        Some(json!({ "type": "Regex", "pattern": self.pattern() }))
+    }
+}
+
+/// A pre-tokenizer shared between a [`BPE`](crate::BPE) and the vocabularies it builds.
+#[derive(Debug, Clone)]
+pub(crate) struct Shared(pub(crate) Arc<dyn PreTokenizer>);
+
+impl PartialEq for Shared {
+    /// Pre-tokenizers are equal if they are the same instance or have the same description.
+    fn eq(&self, other: &Shared) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+            || matches!((self.0.to_json(), other.0.to_json()), (Some(a), Some(b)) if a == b)
+    }
+}
+
+impl Eq for Shared {}
+
+/// Restores a pre-tokenizer from the description written by [`PreTokenizer::to_json`].
+pub(crate) fn from_json(value: &Value) -> Result<Option<Shared>> {
+    let pre_tokenizer: Arc<dyn PreTokenizer> = match value["type"].as_str() {
+        _ if value.is_null() => return Ok(None),
+        Some("Whitespace") => Arc::new(Whitespace),
+        Some("Regex") => match value["pattern"].as_str() {
+            Some(pattern) => Arc::new(RegexPreTokenizer::new(pattern)?),
+            None => return Err(Error::InvalidVocabulary("regex without pattern".into())),
+        },
+        _ => {
+            return Err(Error::InvalidVocabulary(format!(
+                "unknown pre-tokenizer {value}"
+            )))
+        }
+    };
+    Ok(Some(Shared(pre_tokenizer)))
+}
+
+/// Whether `pre_tokenizer` is [`Whitespace`] or a [`RegexPreTokenizer`] with [`GPT2_PATTERN`] or
+/// [`CL100K_PATTERN`], whose pieces only change within the last two pieces as text is appended.
+pub(crate) fn is_built_in(pre_tokenizer: &dyn PreTokenizer) -> bool {
+    let Some(description) = pre_tokenizer.to_json() else {
+        return false;
+    };
+    description == json!({ "type": "Whitespace" })
+        || [GPT2_PATTERN, CL100K_PATTERN]
+            .iter()
+            .any(|pattern| description == json!({ "type": "Regex", "pattern": pattern }))
+}
+
+/// Splits `bytes` into the byte ranges of its pieces.
+///
+/// Without a pre-tokenizer the whole input is one piece. Text between the ranges returned by the
+/// pre-tokenizer and invalid UTF-8 sequences become pieces of their own, so the pieces always
+/// cover the whole input.
+pub(crate) fn split(pre_tokenizer: Option<&dyn PreTokenizer>, bytes: &[u8]) -> Vec<Range<usize>> {
+    let Some(pre_tokenizer) = pre_tokenizer else {
+        let whole = 0..bytes.len();
+        return Some(whole)
+            .filter(|whole| !whole.is_empty())
+            .into_iter()
+            .collect();
+    };
+    let mut pieces = Vec::new();
+    let mut offset = 0;
+    for chunk in bytes.utf8_chunks() {
+        let text = chunk.valid();
+        let mut end = 0;
+        for range in pre_tokenizer.pre_tokenize(text) {
+            let start = range.start.max(end);
+            if start > end {
+                pieces.push(offset + end..offset + start);
+            }
+            if range.end > start {
+                pieces.push(offset + start..offset + range.end);
+                end = range.end;
+            }
+        }
+        if end < text.len() {
+            pieces.push(offset + end..offset + text.len());
+        }
+        offset += text.len();
+        if !chunk.invalid().is_empty() {
+            pieces.push(offset..offset + chunk.invalid().len());
+            offset += chunk.invalid().len();
+        }
+    }
+    pieces
+}
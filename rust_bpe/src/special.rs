@@ -0,0 +1,143 @@
+//! Finding special tokens in the input before it is merged.
+
+use std::ops::Range;
+
+use crate::vocabulary::Vocabulary;
+
+/// Which special tokens [`BPE::encode`](crate::BPE::encode) recognizes in its input.
+///
+/// A recognized special token is emitted as its id, whatever text surrounds it. The text of any
+/// other special token is encoded like ordinary text, so it cannot smuggle a control token into
+/// the output. None are recognized by default, so untrusted input is safe to encode unless
+/// special tokens are allowed explicitly.
+///
+/// ```rust
+/// use rust_bpe::{AllowedSpecial, BpeTrainer, BPE};
+///
+/// let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<|endoftext|>"]));
+/// let vocabulary = bpe.build("hello world");
+/// let end_of_text = vocabulary.special_token_id("<|endoftext|>").unwrap();
+/// assert!(!bpe.encode("hi<|endoftext|>", &vocabulary).contains(&end_of_text));
+///
+/// bpe.set_allowed_special(AllowedSpecial::All);
+/// assert!(bpe.encode("hi<|endoftext|>", &vocabulary).contains(&end_of_text));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AllowedSpecial {
+    /// Every special token is recognized.
+    All,
+    /// No special token is recognized.
+    #[default]
+    None,
+    /// Only the listed special tokens are recognized.
+    Only(Vec<String>),
+    /// Every special token except the listed ones is recognized.
+    Except(Vec<String>),
+}
+
+impl AllowedSpecial {
+    /// Whether the special token spelled `content` is recognized.
+    pub fn allows(&self, content: &[u8]) -> bool {
+        let listed = |tokens: &[String]| tokens.iter().any(|token| token.as_bytes() == content);
+        match self {
+            AllowedSpecial::All => true,
+            AllowedSpecial::None => false,
+            AllowedSpecial::Only(tokens) => listed(tokens),
+            AllowedSpecial::Except(tokens) => !listed(tokens),
+        }
+    }
+}
+
+/// Finds the occurrences of a set of special tokens.
+pub(crate) struct SpecialMatcher<'a> {
+    /// Token bytes and ids, longest first so the longest token wins at a position.
+    tokens: Vec<(&'a [u8], u32)>,
+    /// Whether any token starts with the byte, to skip most positions cheaply.
+    first_bytes: [bool; 256],
+}
+
+impl<'a> SpecialMatcher<'a> {
+    pub(crate) fn new(tokens: impl IntoIterator<Item = (&'a [u8], u32)>) -> SpecialMatcher<'a> {
+        let mut tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|(bytes, _)| !bytes.is_empty())
+            .collect();
+        tokens.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.1.cmp(&b.1)));
+        let mut first_bytes = [false; 256];
+        for (bytes, _) in &tokens {
+            first_bytes[bytes[0] as usize] = true;
+        }
+        SpecialMatcher {
+            tokens,
+            first_bytes,
+        }
+    }
+
+    /// The special tokens of `vocabulary` permitted by `allowed`, and its added tokens.
+    pub(crate) fn for_vocabulary(
+        vocabulary: &'a Vocabulary,
+        allowed: &AllowedSpecial,
+    ) -> SpecialMatcher<'a> {
+        let special = vocabulary.special_tokens().iter().filter_map(|&id| {
+            let bytes = vocabulary.token(id)?;
+            allowed.allows(bytes).then_some((bytes, id))
+        });
+        let added = vocabulary
+            .added_tokens()
+            .iter()
+            .filter_map(|&id| Some((vocabulary.token(id)?, id)));
+        SpecialMatcher::new(special.chain(added))
+    }
+
+    /// Length of the longest token, 0 if there are none.
+    pub(crate) fn max_len(&self) -> usize {
+        self.tokens.first().map_or(0, |(bytes, _)| bytes.len())
+    }
+
+    /// Non-overlapping occurrences in `data`, leftmost first and longest at each position.
+    pub(crate) fn find_iter(&self, data: &[u8]) -> Vec<(Range<usize>, u32)> {
+        let mut found = Vec::new();
+        if self.tokens.is_empty() {
+            return found;
+        }
+        let mut i = 0;
+        while i < data.len() {
+            let token = self.first_bytes[data[i] as usize]
+                .then(|| {
+                    self.tokens
+                        .iter()
+                        .find(|(bytes, _)| data[i..].starts_with(bytes))
+                })
+                .flatten();
+            match token {
+                Some(&(bytes, id)) => {
+                    found.push((i..i + bytes.len(), id));
+                    i += bytes.len();
+                }
+                None => i += 1,
+            }
+        }
+        found
+    }
+
+    /// Splits `data` into the text between the special tokens and the tokens themselves.
+    pub(crate) fn split<'d>(&self, data: &'d [u8]) -> Vec<Segment<'d>> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for (range, id) in self.find_iter(data) {
+            segments.push(Segment::Text(&data[start..range.start]));
+            segments.push(Segment::Special(id));
+            start = range.end;
+        }
+        segments.push(Segment::Text(&data[start..]));
+        segments
+    }
+}
+
+/// A part of the input as split by [`SpecialMatcher::split`].
+pub(crate) enum Segment<'d> {
+    /// Ordinary text, possibly empty.
+    Text(&'d [u8]),
+    /// A special token id.
+    Special(u32),
+}
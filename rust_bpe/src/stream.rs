@@ -0,0 +1,305 @@
+//! Encoding from readers and decoding into writers without holding the whole input.
+//!
+//! A piece may only be encoded once no input that is still to come can change it. The encoder
+//! therefore keeps a tail of its input back: bytes that may begin a special token, text that a
+//! normalizer may still combine with what follows, and the pieces of the pre-tokenizer that may
+//! grow or be split differently once more text arrives.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::encode::encode_word;
+use crate::error::Result;
+use crate::pre_tokenizer;
+use crate::special::SpecialMatcher;
+use crate::vocabulary::Vocabulary;
+use crate::BPE;
+
+/// Bytes read from the underlying reader at once.
+pub(crate) const CHUNK_SIZE: usize = 64 << 10;
+
+/// Pieces at the end of the pending text that the built-in pre-tokenizers may still change.
+pub(crate) const HELD_PIECES: usize = 2;
+
+/// Encodes everything a reader produces, yielding the token ids as they are settled.
+///
+/// The ids are those [`BPE::encode`] returns for the whole input, without the tokens of a
+/// [post-processor](crate::PostProcessor). Input is held back as long as later bytes could still
+/// change its tokens:
+///
+/// - With the built-in pre-tokenizers, [`Whitespace`](crate::Whitespace) and the
+///   [`gpt2`](crate::RegexPreTokenizer::gpt2) and [`cl100k`](crate::RegexPreTokenizer::cl100k)
+///   patterns, text is passed on a few pieces behind the input. Other pre-tokenizers may look
+///   arbitrarily far ahead, so like without a pre-tokenizer the text between two special tokens
+///   is only encoded once it is complete.
+/// - With a normalizer, text is only passed on before ASCII whitespace, where normalization
+///   cannot depend on the following characters.
+///
+/// ```rust
+/// use rust_bpe::{StreamEncoder, Whitespace, BPE};
+///
+/// let mut bpe = BPE::new();
+/// bpe.set_pre_tokenizer(Whitespace);
+/// let vocabulary = bpe.build("abab abc abab");
+///
+/// let ids = StreamEncoder::new(&bpe, &vocabulary, &b"abab abc"[..])
+///     .collect::<Result<Vec<u32>, _>>()
+///     .unwrap();
+/// assert_eq!(ids, bpe.encode("abab abc", &vocabulary));
+/// ```
+pub struct StreamEncoder<'a, R> {
+    bpe: &'a BPE,
+    vocabulary: &'a Vocabulary,
+    specials: SpecialMatcher<'a>,
+    reader: R,
+    /// Pieces kept back from the end of the text, `None` if all of it is kept until it ends.
+    held_pieces: Option<usize>,
+    /// Input that has been read but not split into special tokens and text yet.
+    input: Vec<u8>,
+    /// The length of `input` after it was last searched.
+    held_input: usize,
+    /// Normalized text of the current segment that has not been encoded yet.
+    text: Vec<u8>,
+    /// The length of `text` after it was last split.
+    held_text: usize,
+    /// Whether the current text segment has produced any text, which decides on the prefix
+    /// space.
+    text_started: bool,
+    ids: VecDeque<u32>,
+    done: bool,
+}
+
+impl<'a, R: Read> StreamEncoder<'a, R> {
+    /// Encoder for the input of `reader` with the settings of `bpe` and the merges of
+    /// `vocabulary`.
+    pub fn new(bpe: &'a BPE, vocabulary: &'a Vocabulary, reader: R) -> StreamEncoder<'a, R> {
+        StreamEncoder {
+            bpe,
+            vocabulary,
+            specials: SpecialMatcher::for_vocabulary(vocabulary, &bpe.allowed_special),
+            reader,
+            held_pieces: vocabulary
+                .pre_tokenizer()
+                .filter(|&pre_tokenizer| pre_tokenizer::is_built_in(pre_tokenizer))
+                .map(|_| HELD_PIECES),
+            input: Vec::new(),
+            held_input: 0,
+            text: Vec::new(),
+            held_text: 0,
+            text_started: false,
+            ids: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Reads the next chunk and encodes what it settles, returning whether the input ended.
+    fn read_chunk(&mut self) -> io::Result<bool> {
+        let start = self.input.len();
+        self.input.resize(start + CHUNK_SIZE, 0);
+        let read = loop {
+            match self.reader.read(&mut self.input[start..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.input.truncate(start);
+                    return Err(err);
+                }
+            }
+        };
+        self.input.truncate(start + read);
+        let end = read == 0;
+        // Input that was held back is only searched again once it has doubled, so a long stretch
+        // without a place to cut costs linear time.
+        if end || self.input.len() >= 2 * self.held_input {
+            self.consume(end);
+            self.held_input = self.input.len();
+        }
+        Ok(end)
+    }
+
+    /// Splits the settled part of the input into special tokens and text and encodes the text
+    /// that is complete, everything if the input `end`ed.
+    fn consume(&mut self, end: bool) {
+        let len = self.input.len();
+        // Special tokens starting before `determined` fit into the input, so the matches there
+        // are the matches in the whole input.
+        let determined = match self.specials.max_len() {
+            _ if end => len,
+            0 => len,
+            max_len => (len + 1).saturating_sub(max_len),
+        };
+        let matches: Vec<_> = self
+            .specials
+            .find_iter(&self.input)
+            .into_iter()
+            .filter(|(range, _)| range.start < determined)
+            .collect();
+        let mut settled = match matches.last() {
+            Some((range, _)) if range.end > determined => range.start,
+            _ => determined,
+        };
+        if !end && self.vocabulary.normalizer().is_some() {
+            let text_start = matches
+                .iter()
+                .map(|(range, _)| range.end)
+                .rfind(|&match_end| match_end <= settled)
+                .unwrap_or(0);
+            settled = self.input[text_start..settled]
+                .iter()
+                .rposition(u8::is_ascii_whitespace)
+                .map_or(text_start, |i| text_start + i);
+        }
+
+        let mut start = 0;
+        for (range, id) in matches
+            .into_iter()
+            .filter(|(range, _)| range.end <= settled)
+        {
+            self.push_text(start..range.start);
+            self.encode_text(true);
+            self.text_started = false;
+            self.ids.push_back(id);
+            start = range.end;
+        }
+        self.push_text(start..settled);
+        self.encode_text(end);
+        self.input.drain(..settled);
+    }
+
+    /// Normalizes `range` of the input and appends it to the pending text.
+    fn push_text(&mut self, range: Range<usize>) {
+        let raw = &self.input[range];
+        let normalized;
+        let text = match self.vocabulary.normalizer() {
+            Some(normalizer) => {
+                normalized = normalizer.normalize_bytes(raw);
+                &normalized[..]
+            }
+            None => raw,
+        };
+        if text.is_empty() {
+            return;
+        }
+        if !self.text_started && self.vocabulary.add_prefix_space() && text[0] != b' ' {
+            self.text.push(b' ');
+        }
+        self.text_started = true;
+        self.text.extend_from_slice(text);
+    }
+
+    /// Encodes the pending pieces that can no longer change, all of them if the text is
+    /// `complete`.
+    fn encode_text(&mut self, complete: bool) {
+        let held = match self.held_pieces {
+            _ if complete => 0,
+            Some(held) if self.text.len() >= 2 * self.held_text => held,
+            _ => return,
+        };
+        let pieces = pre_tokenizer::split(self.vocabulary.pre_tokenizer(), &self.text);
+        let settled = pieces.len().saturating_sub(held);
+        for piece in &pieces[..settled] {
+            let ids = encode_word(self.bpe, self.vocabulary, &self.text[piece.clone()]);
+            self.ids.extend(ids);
+        }
+        let end = pieces[..settled].last().map_or(0, |piece| piece.end);
+        self.text.drain(..end);
+        self.held_text = self.text.len();
+    }
+}
+
+impl<R: Read> Iterator for StreamEncoder<'_, R> {
+    type Item = Result<u32>;
+
+    /// The next token id. After an I/O error of the reader the iteration ends.
+    fn next(&mut self) -> Option<Result<u32>> {
+        loop {
+            if let Some(id) = self.ids.pop_front() {
+                return Some(Ok(id));
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_chunk() {
+                Ok(end) => self.done = end,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+    }
+}
+
+/// Decodes token ids into a writer, never splitting a UTF-8 character across two writes.
+///
+/// Tokens often end in the middle of a character, so the bytes of an incomplete UTF-8 sequence
+/// are buffered until the tokens completing it arrive. Everything else is written right away,
+/// also bytes that are not valid UTF-8 at all.
+///
+/// ```rust
+/// use rust_bpe::{StreamDecoder, BPE};
+///
+/// let bpe = BPE::new();
+/// let vocabulary = bpe.build("");
+/// let mut decoder = StreamDecoder::new(&bpe, &vocabulary, Vec::new());
+/// for &byte in "é!".as_bytes() {
+///     decoder.write(&[u32::from(byte)]).unwrap();
+/// }
+/// assert_eq!(decoder.finish().unwrap(), "é!".as_bytes());
+/// ```
+pub struct StreamDecoder<'a, W: Write> {
+    bpe: &'a BPE,
+    vocabulary: &'a Vocabulary,
+    writer: W,
+    /// The start of a character whose remaining bytes have not been decoded yet.
+    incomplete: Vec<u8>,
+}
+
+impl<'a, W: Write> StreamDecoder<'a, W> {
+    /// Decoder writing the tokens of `vocabulary` to `writer`, skipping special tokens if `bpe`
+    /// is set to.
+    pub fn new(bpe: &'a BPE, vocabulary: &'a Vocabulary, writer: W) -> StreamDecoder<'a, W> {
+        StreamDecoder {
+            bpe,
+            vocabulary,
+            writer,
+            incomplete: Vec::new(),
+        }
+    }
+
+    /// Decodes `tokens` like [`BPE::decode`] and writes their bytes, keeping back an incomplete
+    /// character at the end.
+    ///
+    /// Nothing is written if one of the tokens is unknown.
+    pub fn write(&mut self, tokens: &[u32]) -> Result<()> {
+        let bytes = self.bpe.decode(tokens, self.vocabulary)?;
+        self.incomplete.extend_from_slice(&bytes);
+        let complete = self.incomplete.len() - incomplete_len(&self.incomplete);
+        self.writer.write_all(&self.incomplete[..complete])?;
+        self.incomplete.drain(..complete);
+        Ok(())
+    }
+
+    /// Writes the bytes of a character that was never completed, flushes the writer and
+    /// returns it.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(&self.incomplete)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Length of the incomplete UTF-8 sequence at the end of `bytes`, 0 if there is none.
+pub(crate) fn incomplete_len(bytes: &[u8]) -> usize {
+    for len in 1..=bytes.len().min(3) {
+        let tail = &bytes[bytes.len() - len..];
+        if tail[0] & 0xc0 != 0x80 {
+            return match std::str::from_utf8(tail) {
+                Err(err) if err.error_len().is_none() => len,
+                _ => 0,
+            };
+        }
+    }
+    0
+}
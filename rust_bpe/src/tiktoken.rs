@@ -0,0 +1,99 @@
+//! Import and export of tiktoken rank files.
+//!
+//! Every line holds the base64 encoded bytes of a token and its rank, separated by a space.
+//! The rank doubles as the token id. Rank files carry no special tokens.
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::encode::encode_ranks;
+use crate::error::Result;
+use crate::json::invalid;
+use crate::vocabulary::{Merge, MergeMode, Vocabulary};
+
+impl Vocabulary {
+    /// Reads a `.tiktoken` rank file such as `cl100k_base.tiktoken`.
+    ///
+    /// The vocabulary encodes in [`MergeMode::Ranks`], which reproduces tiktoken exactly. It also
+    /// gets the equivalent merge list, so it can be exported to the merge based formats.
+    pub fn from_tiktoken_file(path: impl AsRef<Path>) -> Result<Vocabulary> {
+        let contents = fs::read_to_string(path)?;
+        let lines: Vec<_> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let mut tokens = Vec::new();
+        for line in &lines {
+            let parsed = line.split_once(' ').and_then(|(token, rank)| {
+                let token = STANDARD.decode(token).ok()?;
+                let rank = rank.trim().parse::<usize>().ok()?;
+                Some((token, rank))
+            });
+            let Some((token, rank)) = parsed else {
+                return Err(invalid(format!("invalid rank line {line:?}")));
+            };
+            // Ranks are dense, so every rank has to be below the number of lines.
+            if rank >= lines.len() {
+                return Err(invalid(format!("rank {rank} is out of range")));
+            }
+            if rank >= tokens.len() {
+                tokens.resize(rank + 1, None);
+            }
+            if tokens[rank].replace(token).is_some() {
+                return Err(invalid(format!("rank {rank} is repeated")));
+            }
+        }
+        let tokens = tokens
+            .into_iter()
+            .enumerate()
+            .map(|(rank, token)| token.ok_or_else(|| invalid(format!("rank {rank} is missing"))))
+            .collect::<Result<Vec<_>>>()?;
+
+        let ranked = Vocabulary::from_parts(tokens.clone(), Vec::new(), Vec::new())?;
+        let merges = derive_merges(&ranked);
+        let mut vocabulary = Vocabulary::from_parts(tokens, merges, Vec::new())?;
+        vocabulary.set_merge_mode(MergeMode::Ranks);
+        Ok(vocabulary)
+    }
+
+    /// Writes the regular tokens as a `.tiktoken` rank file, using their ids as ranks.
+    pub fn save_tiktoken_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_tiktoken(BufWriter::new(fs::File::create(path)?))
+    }
+
+    /// Writes the rank file of [`save_tiktoken_file`](Vocabulary::save_tiktoken_file) to
+    /// `writer`.
+    pub fn write_tiktoken(&self, mut writer: impl Write) -> Result<()> {
+        for id in 0..self.len() as u32 {
+            if !self.special_tokens().contains(&id) {
+                let token = STANDARD.encode(self.token(id).unwrap());
+                writeln!(writer, "{token} {id}")?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Recovers the merge that forms each token of a rank-only vocabulary.
+///
+/// Encoding a token with only the lower ranks available yields the two parts it is merged
+/// from. Tokens that cannot be reached this way get no merge.
+fn derive_merges(vocabulary: &Vocabulary) -> Vec<Merge> {
+    (0..vocabulary.len() as u32)
+        .filter_map(|id| {
+            let token = vocabulary.token(id).unwrap();
+            if token.len() < 2 {
+                return None;
+            }
+            match encode_ranks(vocabulary, token, id)[..] {
+                [left, right] => Some(Merge { left, right, id }),
+                _ => None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+//! Merge learning.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::special::SpecialMatcher;
+use crate::vocabulary::Vocabulary;
+
+/// Training configuration consumed by [`BPE::build`](crate::BPE::build).
+///
+/// ```rust
+/// use rust_bpe::{BpeTrainer, BPE};
+///
+/// let trainer = BpeTrainer::new()
+///     .vocab_size(260)
+///     .min_frequency(2)
+///     .special_tokens(["<|endoftext|>"]);
+/// let vocabulary = BPE::with_trainer(trainer).build("abababcabcabc");
+/// assert_eq!(vocabulary.len(), 260);
+/// assert_eq!(vocabulary.merges().len(), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BpeTrainer {
+    vocab_size: usize,
+    min_frequency: u64,
+    max_merges: Option<usize>,
+    special_tokens: Vec<String>,
+}
+
+impl BpeTrainer {
+    /// Default configuration: up to 30 000 tokens, pairs must occur at least twice, no special
+    /// tokens.
+    pub fn new() -> BpeTrainer {
+        BpeTrainer {
+            vocab_size: 30_000,
+            min_frequency: 2,
+            max_merges: None,
+            special_tokens: Vec::new(),
+        }
+    }
+
+    /// Target number of tokens, counting the 256 byte tokens and the special tokens.
+    pub fn vocab_size(mut self, vocab_size: usize) -> Self {
+        self.vocab_size = vocab_size;
+        self
+    }
+
+    /// Pairs occurring less often than this are never merged.
+    pub fn min_frequency(mut self, min_frequency: u64) -> Self {
+        self.min_frequency = min_frequency;
+        self
+    }
+
+    /// Upper bound on the number of merges, independent of the vocabulary size.
+    pub fn max_merges(mut self, max_merges: usize) -> Self {
+        self.max_merges = Some(max_merges);
+        self
+    }
+
+    /// Tokens reserved at the end of the vocabulary. Duplicates are ignored.
+    pub fn special_tokens<I, S>(mut self, tokens: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.special_tokens.clear();
+        for token in tokens {
+            let token = token.into();
+            if !self.special_tokens.contains(&token) {
+                self.special_tokens.push(token);
+            }
+        }
+        self
+    }
+
+    /// Finds the special tokens in training data, which are not learned from.
+    pub(crate) fn special_matcher(&self) -> SpecialMatcher<'_> {
+        SpecialMatcher::new(
+            self.special_tokens
+                .iter()
+                .map(|token| (token.as_bytes(), 0)),
+        )
+    }
+
+    /// Learns a vocabulary on `words`, token sequences with their frequency.
+    pub(crate) fn train(&self, words: Vec<(Vec<u32>, u64)>) -> Vocabulary {
+        let mut vocabulary = Vocabulary::with_bytes();
+        let max_tokens = self.vocab_size.saturating_sub(self.special_tokens.len());
+        let max_merges = self.max_merges.unwrap_or(usize::MAX);
+        learn(
+            &mut vocabulary,
+            words,
+            max_tokens,
+            max_merges,
+            self.min_frequency,
+        );
+        for token in &self.special_tokens {
+            vocabulary.push_special(token);
+        }
+        vocabulary
+    }
+}
+
+impl Default for BpeTrainer {
+    fn default() -> Self {
+        BpeTrainer::new()
+    }
+}
+
+/// Learns merges on `words` and adds them to `vocab` until it holds `max_tokens` tokens or
+/// `max_merges` merges.
+///
+/// Every round merges the most frequent adjacent pair, counted over all words weighted by word
+/// frequency. Ties are broken by the smaller pair so the result never depends on hash order
+/// or, with the `parallel` feature, on the number of threads. Learning stops early once no pair
+/// occurs at least `min_frequency` times.
+///
+/// Pair counts are only recounted for the words containing the merged pair, found through an
+/// index from pairs to words. The best pair comes from a max-heap whose entries may be stale: a
+/// popped entry whose count no longer matches is pushed again with the current count.
+fn learn(
+    vocab: &mut Vocabulary,
+    mut words: Vec<(Vec<u32>, u64)>,
+    max_tokens: usize,
+    max_merges: usize,
+    min_frequency: u64,
+) {
+    let PairStats {
+        mut counts,
+        mut where_to_update,
+    } = PairStats::collect(&words);
+    let mut queue: BinaryHeap<Candidate> = counts
+        .iter()
+        .map(|(&pair, &count)| Candidate { count, pair })
+        .collect();
+
+    while vocab.len() < max_tokens && vocab.merges().len() < max_merges {
+        let Some(top) = queue.pop() else { break };
+        let count = counts.get(&top.pair).copied().unwrap_or(0);
+        if top.count != count {
+            if count > 0 {
+                queue.push(Candidate { count, ..top });
+            }
+            continue;
+        }
+        if count < min_frequency {
+            break;
+        }
+
+        let (left, right) = top.pair;
+        let id = vocab.push_merge(left, right);
+        let mut changed = HashMap::new();
+        for index in where_to_update.remove(&top.pair).unwrap_or_default() {
+            let (word, freq) = &mut words[index];
+            for pair in word.windows(2) {
+                let pair = (pair[0], pair[1]);
+                let count = counts.get_mut(&pair).unwrap();
+                changed.entry(pair).or_insert(*count);
+                *count -= *freq;
+            }
+            merge_word(word, left, right, id);
+            for pair in word.windows(2) {
+                let pair = (pair[0], pair[1]);
+                let count = counts.entry(pair).or_default();
+                changed.entry(pair).or_insert(*count);
+                *count += *freq;
+                where_to_update.entry(pair).or_default().insert(index);
+            }
+        }
+        for (pair, before) in changed {
+            match counts[&pair] {
+                0 => {
+                    counts.remove(&pair);
+                    where_to_update.remove(&pair);
+                }
+                count if count > before => queue.push(Candidate { count, pair }),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A pair with its count when it was queued, ordered by count and then by the smaller pair.
+#[derive(Debug, PartialEq, Eq)]
+struct Candidate {
+    count: u64,
+    pair: (u32, u32),
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        self.count
+            .cmp(&other.count)
+            .then_with(|| other.pair.cmp(&self.pair))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Frequency of every adjacent pair and the indexes of the words it occurs in.
+#[derive(Default)]
+struct PairStats {
+    counts: HashMap<(u32, u32), u64>,
+    where_to_update: HashMap<(u32, u32), HashSet<usize>>,
+}
+
+impl PairStats {
+    /// Statistics of all `words`.
+    #[cfg(not(feature = "parallel"))]
+    fn collect(words: &[(Vec<u32>, u64)]) -> PairStats {
+        let mut stats = PairStats::default();
+        for (index, (word, freq)) in words.iter().enumerate() {
+            stats.add(index, word, *freq);
+        }
+        stats
+    }
+
+    /// Statistics of all `words`, collected on the thread pool.
+    #[cfg(feature = "parallel")]
+    fn collect(words: &[(Vec<u32>, u64)]) -> PairStats {
+        words
+            .par_iter()
+            .enumerate()
+            .fold(PairStats::default, |mut stats, (index, (word, freq))| {
+                stats.add(index, word, *freq);
+                stats
+            })
+            .reduce(PairStats::default, PairStats::merge)
+    }
+
+    fn add(&mut self, index: usize, word: &[u32], freq: u64) {
+        for pair in word.windows(2) {
+            let pair = (pair[0], pair[1]);
+            *self.counts.entry(pair).or_default() += freq;
+            self.where_to_update.entry(pair).or_default().insert(index);
+        }
+    }
+
+    /// Combines the statistics of two disjoint sets of words.
+    #[cfg(feature = "parallel")]
+    fn merge(mut self, other: PairStats) -> PairStats {
+        for (pair, count) in other.counts {
+            *self.counts.entry(pair).or_default() += count;
+        }
+        for (pair, indexes) in other.where_to_update {
+            self.where_to_update
+                .entry(pair)
+                .or_default()
+                .extend(indexes);
+        }
+        self
+    }
+}
+
+/// Replaces every non-overlapping occurrence of `(left, right)` in `word`, left to right.
+pub(crate) fn merge_word(word: &mut Vec<u32>, left: u32, right: u32, id: u32) {
+    let mut read = 0;
+    let mut write = 0;
+    while read < word.len() {
+        if read + 1 < word.len() && word[read] == left && word[read + 1] == right {
+            word[write] = id;
+            read += 2;
+        } else {
+            word[write] = word[read];
+            read += 1;
+        }
+        write += 1;
+    }
+    word.truncate(write);
+}
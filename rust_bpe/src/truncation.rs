@@ -0,0 +1,126 @@
+//! Limiting encodings to a maximum length.
+
+use crate::encoding::Encoding;
+
+/// The end of a sequence that tokens are cut from or added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Side {
+    Left,
+    #[default]
+    Right,
+}
+
+/// Which sequence of a pair is shortened to respect the maximum length. A single sequence is
+/// always shortened itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncationStrategy {
+    /// Cuts tokens from the longer sequence until both are equally long, then from both.
+    #[default]
+    LongestFirst,
+    /// Only cuts tokens from the first sequence.
+    OnlyFirst,
+    /// Only cuts tokens from the second sequence.
+    OnlySecond,
+}
+
+/// Truncation applied by [`BPE::encode_with_offsets`](crate::BPE::encode_with_offsets) and
+/// [`BPE::encode_batch_with_offsets`](crate::BPE::encode_batch_with_offsets).
+///
+/// The tokens that are cut off are kept as [`Encoding::overflowing`] windows of the same
+/// maximum length, so long inputs can be processed piece by piece. The maximum length includes
+/// the special tokens added by the [`PostProcessor`](crate::PostProcessor) of the vocabulary.
+///
+/// ```rust
+/// use rust_bpe::{Side, Truncation, BPE};
+///
+/// let mut bpe = BPE::new();
+/// let vocabulary = bpe.build("");
+/// bpe.set_truncation(Truncation::new(4).stride(1).side(Side::Right));
+///
+/// let encoding = bpe.encode_with_offsets("abcdefghij", &vocabulary);
+/// assert_eq!(encoding.tokens(), ["a", "b", "c", "d"]);
+/// let windows: Vec<_> = encoding.overflowing().iter().map(|e| e.tokens().concat()).collect();
+/// assert_eq!(windows, ["defg", "ghij"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncation {
+    pub(crate) max_length: usize,
+    pub(crate) stride: usize,
+    pub(crate) strategy: TruncationStrategy,
+    pub(crate) side: Side,
+}
+
+impl Truncation {
+    /// Keeps at most `max_length` tokens, cut from the right, without overlapping windows.
+    pub fn new(max_length: usize) -> Truncation {
+        Truncation {
+            max_length,
+            stride: 0,
+            strategy: TruncationStrategy::LongestFirst,
+            side: Side::Right,
+        }
+    }
+
+    /// Number of tokens repeated at the start of every overflowing window from the end of the
+    /// previous one. A stride of `max_length` or more is reduced to `max_length - 1`.
+    pub fn stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// Which sequence of a pair is shortened.
+    pub fn strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The end that tokens are cut from.
+    pub fn side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// Most tokens kept.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Shortens `encoding` to leave room for `added` special tokens within the maximum length.
+    pub(crate) fn apply(&self, encoding: &mut Encoding, added: usize) {
+        let max_length = self.max_length.saturating_sub(added);
+        encoding.truncate(max_length, self.stride, self.side);
+    }
+
+    /// Shortens `first` and `second` to leave room for `added` special tokens within the maximum
+    /// length.
+    ///
+    /// If the sequence to shorten cannot absorb all of the excess, the pair stays longer than
+    /// the maximum.
+    pub(crate) fn apply_pair(&self, first: &mut Encoding, second: &mut Encoding, added: usize) {
+        let max_length = self.max_length.saturating_sub(added);
+        let (len_first, len_second) = (first.len(), second.len());
+        if len_first + len_second <= max_length {
+            return;
+        }
+        let (keep_first, keep_second) = match self.strategy {
+            TruncationStrategy::LongestFirst => {
+                let half = max_length / 2;
+                if len_first.min(len_second) <= half {
+                    let shorter = len_first.min(len_second);
+                    if len_first <= len_second {
+                        (len_first, max_length - shorter)
+                    } else {
+                        (max_length - shorter, len_second)
+                    }
+                } else {
+                    // The first sequence keeps the odd token.
+                    (max_length - half, half)
+                }
+            }
+            TruncationStrategy::OnlyFirst => (max_length.saturating_sub(len_second), len_second),
+            TruncationStrategy::OnlySecond => (len_first, max_length.saturating_sub(len_first)),
+        };
+        first.truncate(keep_first, self.stride, self.side);
+        second.truncate(keep_second, self.stride, self.side);
+    }
+}
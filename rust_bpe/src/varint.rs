@@ -0,0 +1,89 @@
+//! LEB128 variable-length integers and a cursor over binary input.
+
+use crate::error::{Error, Result};
+
+/// Appends `value` in unsigned LEB128: seven bits per byte, least significant first, the high
+/// bit set on every byte but the last.
+pub(crate) fn write(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads binary input front to back, failing with [`Error::InvalidCompressedData`] when it
+/// ends early.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data }
+    }
+
+    /// Number of bytes left.
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(truncated());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Takes everything that is left.
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    /// Reads a value written by [`write`], rejecting encodings longer than 64 bits.
+    pub(crate) fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::InvalidCompressedData(
+            "varint overflows 64 bits".into(),
+        ))
+    }
+
+    /// Reads a varint that has to fit in `u32`.
+    pub(crate) fn varint_u32(&mut self) -> Result<u32> {
+        let value = self.varint()?;
+        u32::try_from(value)
+            .map_err(|_| Error::InvalidCompressedData(format!("{value} does not fit in 32 bits")))
+    }
+
+    /// Reads a varint that has to fit in `usize`.
+    pub(crate) fn varint_usize(&mut self) -> Result<usize> {
+        let value = self.varint()?;
+        usize::try_from(value)
+            .map_err(|_| Error::InvalidCompressedData(format!("length {value} is too large")))
+    }
+}
+
+fn truncated() -> Error {
+    Error::InvalidCompressedData("unexpected end of data".into())
+}
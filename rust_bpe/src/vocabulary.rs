@@ -0,0 +1,370 @@
+//! The learned model: token byte strings and the ordered merge list.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::cache::Revision;
+use crate::error::{Error, Result};
+use crate::normalizer::Normalizer;
+use crate::post_processor::PostProcessor;
+use crate::pre_tokenizer::{PreTokenizer, Shared};
+
+/// A single learned merge rule: the adjacent pair `(left, right)` is replaced by `id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Merge {
+    pub left: u32,
+    pub right: u32,
+    pub id: u32,
+}
+
+/// How [`BPE::encode`](crate::BPE::encode) decides which adjacent tokens to merge next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeMode {
+    /// Apply the pair with the lowest-ranked entry in [`Vocabulary::merges`].
+    #[default]
+    Merges,
+    /// Merge the adjacent pair whose concatenation is the token with the lowest id, like
+    /// tiktoken does. The token ids are the ranks, no merge list is needed. If a whole
+    /// pre-tokenized piece is a token, it is used directly.
+    Ranks,
+}
+
+/// Vocabulary produced by [`BPE::build`](crate::BPE::build).
+///
+/// Every token id maps to the byte string it stands for. Every single byte is a token, every
+/// further token is introduced by a [`Merge`] or is a special token. The position of a merge in
+/// [`merges`](Vocabulary::merges) is its rank: lower ranks were learned first and are applied
+/// first when encoding.
+///
+/// Vocabularies trained by this crate use ids `0..256` for the single bytes and reserve the
+/// special tokens after the merges. Imported vocabularies keep the ids of their source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    tokens: Vec<Vec<u8>>,
+    ids: HashMap<Vec<u8>, u32>,
+    byte_ids: [u32; 256],
+    merges: Vec<Merge>,
+    ranks: HashMap<(u32, u32), usize>,
+    special_tokens: Vec<u32>,
+    added_tokens: Vec<u32>,
+    normalizer: Option<Normalizer>,
+    add_prefix_space: bool,
+    pre_tokenizer: Option<Shared>,
+    merge_mode: MergeMode,
+    post_processor: Option<PostProcessor>,
+    revision: Revision,
+}
+
+impl Vocabulary {
+    /// Vocabulary containing only the 256 single-byte tokens.
+    pub(crate) fn with_bytes() -> Vocabulary {
+        let tokens: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).collect();
+        Vocabulary {
+            ids: tokens.iter().cloned().zip(0..).collect(),
+            tokens,
+            byte_ids: std::array::from_fn(|b| b as u32),
+            merges: Vec::new(),
+            ranks: HashMap::new(),
+            special_tokens: Vec::new(),
+            added_tokens: Vec::new(),
+            normalizer: None,
+            add_prefix_space: false,
+            pre_tokenizer: None,
+            merge_mode: MergeMode::Merges,
+            post_processor: None,
+            revision: Revision::new(),
+        }
+    }
+
+    /// Assembles a vocabulary from its parts, checking that they are consistent.
+    ///
+    /// `tokens[id]` holds the bytes of token `id`. Regular tokens must be unique and include
+    /// every single byte, and every merge must combine two regular tokens into the regular token
+    /// spelling their concatenation.
+    pub(crate) fn from_parts(
+        tokens: Vec<Vec<u8>>,
+        merges: Vec<Merge>,
+        special_tokens: Vec<u32>,
+    ) -> Result<Vocabulary> {
+        let invalid = |message: String| Err(Error::InvalidVocabulary(message));
+
+        let mut is_special = vec![false; tokens.len()];
+        for &id in &special_tokens {
+            match is_special.get_mut(id as usize) {
+                Some(flag) if !*flag => *flag = true,
+                _ => return invalid(format!("invalid special token id {id}")),
+            }
+        }
+
+        let mut ids = HashMap::with_capacity(tokens.len());
+        for (id, token) in tokens.iter().enumerate() {
+            if token.is_empty() {
+                return invalid(format!("token {id} is empty"));
+            }
+            if !is_special[id] && ids.insert(token.clone(), id as u32).is_some() {
+                return invalid(format!("token {id} is a duplicate"));
+            }
+        }
+        let mut byte_ids = [0; 256];
+        for (byte, slot) in byte_ids.iter_mut().enumerate() {
+            match ids.get(&[byte as u8][..]) {
+                Some(&id) => *slot = id,
+                None => return invalid(format!("byte {byte:#04x} has no token")),
+            }
+        }
+
+        let mut ranks = HashMap::with_capacity(merges.len());
+        for (rank, merge) in merges.iter().enumerate() {
+            let regular = |id: u32| tokens.get(id as usize).filter(|_| !is_special[id as usize]);
+            let (left, right, result) =
+                match (regular(merge.left), regular(merge.right), regular(merge.id)) {
+                    (Some(left), Some(right), Some(result)) => (left, right, result),
+                    _ => return invalid(format!("merge {rank} refers to an invalid token")),
+                };
+            if result.len() != left.len() + right.len()
+                || !result.starts_with(left)
+                || !result.ends_with(right)
+            {
+                return invalid(format!("merge {rank} does not produce its token"));
+            }
+            if ranks.insert((merge.left, merge.right), rank).is_some() {
+                return invalid(format!("merge {rank} is a duplicate"));
+            }
+        }
+
+        Ok(Vocabulary {
+            tokens,
+            ids,
+            byte_ids,
+            merges,
+            ranks,
+            special_tokens,
+            added_tokens: Vec::new(),
+            normalizer: None,
+            add_prefix_space: false,
+            pre_tokenizer: None,
+            merge_mode: MergeMode::Merges,
+            post_processor: None,
+            revision: Revision::new(),
+        })
+    }
+
+    /// Records the merge of `left` and `right` and returns the id of the merged token.
+    ///
+    /// Different pairs can spell the same bytes, in that case the existing token is reused.
+    pub(crate) fn push_merge(&mut self, left: u32, right: u32) -> u32 {
+        let mut bytes = self.tokens[left as usize].clone();
+        bytes.extend_from_slice(&self.tokens[right as usize]);
+        let id = match self.ids.get(&bytes) {
+            Some(&id) => id,
+            None => {
+                let id = self.tokens.len() as u32;
+                self.ids.insert(bytes.clone(), id);
+                self.tokens.push(bytes);
+                id
+            }
+        };
+        self.ranks.insert((left, right), self.merges.len());
+        self.merges.push(Merge { left, right, id });
+        self.revision = Revision::new();
+        id
+    }
+
+    /// Reserves a special token for `content` and returns its id.
+    pub(crate) fn push_special(&mut self, content: &str) -> u32 {
+        let id = self.tokens.len() as u32;
+        self.tokens.push(content.as_bytes().to_vec());
+        self.special_tokens.push(id);
+        id
+    }
+
+    /// Number of tokens, including the 256 single-byte tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Always `false`, the byte tokens are part of every vocabulary.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Bytes represented by token `id`, or `None` if the id is out of range.
+    pub fn token(&self, id: u32) -> Option<&[u8]> {
+        self.tokens.get(id as usize).map(Vec::as_slice)
+    }
+
+    /// Id of the regular (non-special) token spelling `bytes`.
+    pub fn token_id(&self, bytes: &[u8]) -> Option<u32> {
+        self.ids.get(bytes).copied()
+    }
+
+    /// Id of the token for the single byte `byte`.
+    pub fn byte_id(&self, byte: u8) -> u32 {
+        self.byte_ids[byte as usize]
+    }
+
+    /// Learned merges in rank order.
+    pub fn merges(&self) -> &[Merge] {
+        &self.merges
+    }
+
+    /// Ids of the special tokens, in the order they were reserved.
+    pub fn special_tokens(&self) -> &[u32] {
+        &self.special_tokens
+    }
+
+    /// Ids of the regular tokens that are matched as a whole before merging, like special tokens.
+    ///
+    /// Unlike special tokens they are always matched, whatever
+    /// [`allowed_special`](crate::BPE::allowed_special) says, and they are kept when special
+    /// tokens are skipped while decoding. They come from the non-special `added_tokens` of a
+    /// Hugging Face `tokenizer.json`.
+    pub fn added_tokens(&self) -> &[u32] {
+        &self.added_tokens
+    }
+
+    /// Matches the regular tokens `ids` as a whole before merging.
+    pub(crate) fn set_added_tokens(&mut self, mut ids: Vec<u32>) -> Result<()> {
+        ids.sort_unstable();
+        ids.dedup();
+        if let Some(id) = ids
+            .iter()
+            .find(|&&id| id as usize >= self.len() || self.special_tokens.contains(&id))
+        {
+            return Err(Error::InvalidVocabulary(format!(
+                "invalid added token id {id}"
+            )));
+        }
+        self.added_tokens = ids;
+        Ok(())
+    }
+
+    /// Id of the special token spelled `content`.
+    pub fn special_token_id(&self, content: &str) -> Option<u32> {
+        self.special_tokens
+            .iter()
+            .copied()
+            .find(|&id| self.tokens[id as usize] == content.as_bytes())
+    }
+
+    /// Registers `content` as a special token and returns its id.
+    ///
+    /// New special tokens get the next free id, which never changes afterwards. Registering a
+    /// special token again returns its existing id.
+    ///
+    /// ```rust
+    /// use rust_bpe::{AllowedSpecial, BPE};
+    ///
+    /// let mut bpe = BPE::new();
+    /// bpe.set_allowed_special(AllowedSpecial::All);
+    /// let mut vocabulary = bpe.build("abababcab");
+    /// let pad = vocabulary.add_special_token("<pad>");
+    /// assert_eq!(pad as usize, vocabulary.len() - 1);
+    /// assert_eq!(vocabulary.add_special_token("<pad>"), pad);
+    /// assert_eq!(bpe.encode("ab<pad>", &vocabulary), [256, pad]);
+    /// ```
+    pub fn add_special_token(&mut self, content: &str) -> u32 {
+        match self.special_token_id(content) {
+            Some(id) => id,
+            None => self.push_special(content),
+        }
+    }
+
+    /// Rank of the merge `(left, right)`, if it was learned.
+    pub fn merge_rank(&self, left: u32, right: u32) -> Option<usize> {
+        self.ranks.get(&(left, right)).copied()
+    }
+
+    /// Normalizer applied to the input before pre-tokenization.
+    pub fn normalizer(&self) -> Option<&Normalizer> {
+        self.normalizer.as_ref()
+    }
+
+    /// Normalizes the input with `normalizer` before pre-tokenization.
+    ///
+    /// Like the pre-tokenizer, this should be the normalizer the vocabulary was trained with.
+    pub fn set_normalizer(&mut self, normalizer: Normalizer) {
+        self.normalizer = Some(normalizer);
+    }
+
+    /// Leaves the input as it is.
+    pub fn remove_normalizer(&mut self) {
+        self.normalizer = None;
+    }
+
+    /// Whether a space is prepended to input that does not start with one, so the first word is
+    /// encoded like any other word.
+    pub fn add_prefix_space(&self) -> bool {
+        self.add_prefix_space
+    }
+
+    /// Changes whether a space is prepended to input that does not start with one.
+    pub fn set_add_prefix_space(&mut self, add_prefix_space: bool) {
+        self.add_prefix_space = add_prefix_space;
+    }
+
+    /// Pre-tokenizer that splits the input before merging, `None` if the input is merged as a
+    /// whole.
+    pub fn pre_tokenizer(&self) -> Option<&dyn PreTokenizer> {
+        self.pre_tokenizer.as_ref().map(|shared| &*shared.0)
+    }
+
+    /// Splits the input with `pre_tokenizer` before merging.
+    ///
+    /// Encoding only reproduces the segmentation of the training data if this is the
+    /// pre-tokenizer the vocabulary was trained with.
+    pub fn set_pre_tokenizer(&mut self, pre_tokenizer: impl PreTokenizer + 'static) {
+        self.pre_tokenizer = Some(Shared(Arc::new(pre_tokenizer)));
+    }
+
+    /// Merges the input as a whole, without pre-tokenization.
+    pub fn remove_pre_tokenizer(&mut self) {
+        self.pre_tokenizer = None;
+    }
+
+    pub(crate) fn set_shared_pre_tokenizer(&mut self, pre_tokenizer: Option<Shared>) {
+        self.pre_tokenizer = pre_tokenizer;
+    }
+
+    /// How the encoder picks the next merge.
+    pub fn merge_mode(&self) -> MergeMode {
+        self.merge_mode
+    }
+
+    /// Changes how the encoder picks the next merge.
+    pub fn set_merge_mode(&mut self, merge_mode: MergeMode) {
+        self.merge_mode = merge_mode;
+        self.revision = Revision::new();
+    }
+
+    /// Post-processor adding special tokens around encoded inputs.
+    pub fn post_processor(&self) -> Option<&PostProcessor> {
+        self.post_processor.as_ref()
+    }
+
+    /// Adds special tokens around encoded inputs with `post_processor`.
+    ///
+    /// Fails if a token of the template is not a special token of the vocabulary.
+    pub fn set_post_processor(&mut self, post_processor: PostProcessor) -> Result<()> {
+        if let Some(token) = post_processor
+            .special_tokens()
+            .find(|token| self.special_token_id(token).is_none())
+        {
+            return Err(Error::InvalidTemplate(format!(
+                "{token:?} is not a special token"
+            )));
+        }
+        self.post_processor = Some(post_processor);
+        Ok(())
+    }
+
+    /// Leaves encoded inputs as they are.
+    pub fn remove_post_processor(&mut self) {
+        self.post_processor = None;
+    }
+
+    /// Changes whenever the ids of a pre-tokenized piece may change.
+    pub(crate) fn revision(&self) -> Revision {
+        self.revision
+    }
+}
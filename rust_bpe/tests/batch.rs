@@ -0,0 +1,42 @@
+use rust_bpe::{BpeTrainer, Error, RegexPreTokenizer, Vocabulary, BPE};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shared_across_threads() {
+    assert_send_sync::<Vocabulary>();
+    assert_send_sync::<BPE>();
+}
+
+#[test]
+fn order_is_kept() {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().vocab_size(400));
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    let documents: Vec<String> = (0..2_000)
+        .map(|i| format!("document {i} has {} words", i % 17))
+        .collect();
+    let vocabulary = bpe.build(&documents.concat());
+
+    let batch = bpe.encode_batch(&documents, &vocabulary);
+    assert_eq!(batch.len(), documents.len());
+    for (document, tokens) in documents.iter().zip(&batch) {
+        assert_eq!(*tokens, bpe.encode(document, &vocabulary));
+    }
+
+    let decoded = bpe.decode_batch(&batch, &vocabulary).unwrap();
+    for (document, bytes) in documents.iter().zip(decoded) {
+        assert_eq!(document.as_bytes(), bytes);
+    }
+    assert!(bpe.encode_batch(&[] as &[&str], &vocabulary).is_empty());
+}
+
+#[test]
+fn first_unknown_token_is_reported() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build("abab");
+    let sequences = [vec![97], vec![1_000], vec![2_000]];
+    assert!(matches!(
+        bpe.decode_batch(&sequences, &vocabulary),
+        Err(Error::UnknownToken(1_000))
+    ));
+}
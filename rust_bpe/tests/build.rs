@@ -0,0 +1,37 @@
+use rust_bpe::{Merge, BPE};
+
+#[test]
+fn most_frequent_pair_is_merged_first() {
+    // `ab` occurs four times, then the merged `ab ab` twice, everything else only once.
+    let vocabulary = BPE::new().build("abababcab");
+    assert_eq!(
+        vocabulary.merges(),
+        [
+            Merge {
+                left: 97,
+                right: 98,
+                id: 256
+            },
+            Merge {
+                left: 256,
+                right: 256,
+                id: 257
+            },
+        ]
+    );
+    assert_eq!(vocabulary.len(), 258);
+    assert_eq!(vocabulary.token(256), Some(&b"ab"[..]));
+    assert_eq!(vocabulary.token(257), Some(&b"abab"[..]));
+    assert_eq!(vocabulary.token(b'c'.into()), Some(&b"c"[..]));
+    assert_eq!(vocabulary.token(258), None);
+}
+
+#[test]
+fn empty_input_keeps_the_byte_tokens() {
+    let vocabulary = BPE::new().build("");
+    assert!(vocabulary.merges().is_empty());
+    assert_eq!(vocabulary.len(), 256);
+    for byte in 0..=255u8 {
+        assert_eq!(vocabulary.token(byte.into()), Some(&[byte][..]));
+    }
+}
@@ -0,0 +1,81 @@
+use rust_bpe::{BpeTrainer, CacheStats, MergeMode, Whitespace, BPE};
+
+fn bpe(capacity: usize) -> BPE {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1));
+    bpe.set_pre_tokenizer(Whitespace);
+    bpe.set_cache_capacity(capacity);
+    bpe
+}
+
+#[test]
+fn disabled_by_default() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build("abab");
+    bpe.encode("abab abab", &vocabulary);
+    assert_eq!(bpe.cache_capacity(), 0);
+    assert_eq!(bpe.cache_stats(), CacheStats::default());
+}
+
+#[test]
+fn same_ids_as_without_cache() {
+    let documents: Vec<String> = (0..500)
+        .map(|i| format!("word{} and word{} again", i % 13, i % 7))
+        .collect();
+    let uncached = bpe(0);
+    let vocabulary = uncached.build(&documents.concat());
+    let cached = bpe(8);
+
+    let expected = uncached.encode_batch(&documents, &vocabulary);
+    assert_eq!(cached.encode_batch(&documents, &vocabulary), expected);
+    // Again with a warm cache.
+    assert_eq!(cached.encode_batch(&documents, &vocabulary), expected);
+    let stats = cached.cache_stats();
+    assert_eq!(stats.hits + stats.misses, 2 * 500 * 7);
+    assert!(stats.hits > stats.misses);
+    assert!(stats.len <= 8);
+}
+
+#[test]
+fn least_recently_used_is_evicted() {
+    let bpe = bpe(2);
+    let vocabulary = bpe.build("aa bb cc");
+    let misses = |text: &str| {
+        let before = bpe.cache_stats().misses;
+        bpe.encode(text, &vocabulary);
+        bpe.cache_stats().misses - before
+    };
+
+    assert_eq!(misses("aa"), 1);
+    assert_eq!(misses("bb"), 1);
+    assert_eq!(misses("aa"), 0);
+    // `bb` is the least recently used word and makes room for `cc`.
+    assert_eq!(misses("cc"), 1);
+    assert_eq!(misses("aa"), 0);
+    assert_eq!(misses("bb"), 1);
+    assert_eq!(bpe.cache_stats().len, 2);
+
+    bpe.clear_cache();
+    assert_eq!(
+        bpe.cache_stats(),
+        CacheStats {
+            capacity: 2,
+            ..CacheStats::default()
+        }
+    );
+}
+
+#[test]
+fn not_shared_between_vocabularies() {
+    let bpe = bpe(100);
+    let first = bpe.build("ab ab");
+    let mut second = bpe.build("bc bc");
+    assert_eq!(bpe.encode("ab", &first), [256]);
+    assert_eq!(bpe.encode("ab", &second), [97, 98]);
+    assert_eq!(bpe.encode("ab", &first), [256]);
+
+    // Changing how pieces are merged invalidates the cached ids as well.
+    assert_eq!(bpe.encode("bc", &second), [256]);
+    second.set_merge_mode(MergeMode::Ranks);
+    assert_eq!(bpe.encode("bc", &second), [256]);
+    assert_eq!(bpe.cache_stats().hits, 0);
+}
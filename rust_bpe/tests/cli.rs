@@ -0,0 +1,196 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const CORPUS: &str = "the cat sat on the mat. the cat ate the rat.\n";
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust_bpe"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The tool may exit before reading its input, when the arguments are invalid.
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_bpe_cli_{name}_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Trains on the corpus from standard input and writes the vocabulary into `dir`.
+fn train(dir: &Path) -> String {
+    let vocab = dir.join("vocab.json").to_str().unwrap().to_owned();
+    let output = run(
+        &[
+            "train",
+            "--vocab-size",
+            "270",
+            "--min-frequency",
+            "1",
+            "--special-token",
+            "<|end|>",
+            "-o",
+            &vocab,
+        ],
+        CORPUS.as_bytes(),
+    );
+    assert!(output.status.success(), "{output:?}");
+    vocab
+}
+
+#[test]
+fn round_trip_through_pipes() {
+    let dir = temp_dir("round_trip");
+    let vocab = train(&dir);
+
+    let text = "the rat sat on the cat<|end|>";
+    let encoded = run(
+        &["encode", "-v", &vocab, "--allow-special"],
+        text.as_bytes(),
+    );
+    assert!(encoded.status.success(), "{encoded:?}");
+    let ids = String::from_utf8(encoded.stdout).unwrap();
+    assert!(ids.trim_end().ends_with(" 269"));
+
+    let decoded = run(&["decode", "-v", &vocab], ids.as_bytes());
+    assert!(decoded.status.success(), "{decoded:?}");
+    assert_eq!(decoded.stdout, text.as_bytes());
+
+    let skipped = run(
+        &["decode", "-v", &vocab, "--skip-special-tokens"],
+        ids.as_bytes(),
+    );
+    assert_eq!(skipped.stdout, b"the rat sat on the cat");
+}
+
+#[test]
+fn lines_in_json() {
+    let dir = temp_dir("lines");
+    let vocab = train(&dir);
+
+    let encoded = run(
+        &["encode", "-v", &vocab, "--lines", "--format", "json"],
+        b"the cat\nthe mat\n",
+    );
+    let batch: Vec<Vec<u32>> = serde_json::from_slice(&encoded.stdout).unwrap();
+    assert_eq!(batch.len(), 2);
+
+    let decoded = run(
+        &["decode", "-v", &vocab, "--lines", "--format", "json"],
+        &encoded.stdout,
+    );
+    assert_eq!(decoded.stdout, b"the cat\nthe mat\n");
+}
+
+#[test]
+fn convert_and_inspect() {
+    let dir = temp_dir("convert");
+    let vocab = train(&dir);
+    let tiktoken = dir.join("vocab.tiktoken").to_str().unwrap().to_owned();
+    let hf = dir.join("tokenizer.json").to_str().unwrap().to_owned();
+
+    for (format, path) in [("tiktoken", &tiktoken), ("hf", &hf)] {
+        let output = run(&["convert", &vocab, "--format", format, "-o", path], b"");
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    // The formats are detected from the file contents.
+    let expected = run(&["encode", "-v", &vocab], b"the cat").stdout;
+    assert_eq!(run(&["encode", "-v", &hf], b"the cat").stdout, expected);
+    assert_eq!(
+        run(&["encode", "-v", &tiktoken], b"the cat").stdout,
+        expected
+    );
+
+    let gpt2_vocab = dir.join("vocab.json").to_str().unwrap().to_owned();
+    let gpt2_merges = dir.join("merges.txt").to_str().unwrap().to_owned();
+    let output = run(
+        &[
+            "convert",
+            &vocab,
+            "--format",
+            "gpt2",
+            "-o",
+            &gpt2_vocab,
+            "--output-merges",
+            &gpt2_merges,
+        ],
+        b"",
+    );
+    assert!(output.status.success(), "{output:?}");
+    let output = run(&["convert", &vocab, "--format", "gpt2"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    // Giving the merges selects the GPT-2 format.
+    assert_eq!(
+        run(
+            &["encode", "-v", &gpt2_vocab, "--merges", &gpt2_merges],
+            b"the cat"
+        )
+        .stdout,
+        expected
+    );
+
+    let summary = run(&["inspect", "-v", &hf, "--format", "json"], b"");
+    let summary: serde_json::Value = serde_json::from_slice(&summary.stdout).unwrap();
+    assert_eq!(summary["tokens"], 270);
+    assert_eq!(summary["special_tokens"]["<|end|>"], 269);
+}
+
+#[test]
+fn errors_exit_with_a_message() {
+    let dir = temp_dir("errors");
+    let vocab = train(&dir);
+
+    let missing = run(&["encode", "-v", "does/not/exist.json"], b"text");
+    assert_eq!(missing.status.code(), Some(1));
+    let message = String::from_utf8(missing.stderr).unwrap();
+    assert!(message.starts_with("rust_bpe: error: does/not/exist.json"));
+
+    let invalid = run(&["decode", "-v", &vocab], b"258 cat");
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(String::from_utf8(invalid.stderr)
+        .unwrap()
+        .contains("invalid token id \"cat\""));
+
+    let unknown = run(&["decode", "-v", &vocab], b"100000");
+    assert_eq!(unknown.status.code(), Some(1));
+
+    let usage = run(&["encode", "--no-such-flag"], b"");
+    assert_eq!(usage.status.code(), Some(2));
+}
+
+#[test]
+fn compress_round_trip() {
+    let data: Vec<u8> = CORPUS.repeat(50).bytes().chain([0, 255, 128]).collect();
+    let compressed = run(&["compress"], &data);
+    assert!(compressed.status.success(), "{compressed:?}");
+    assert!(compressed.stdout.len() < data.len() / 2);
+    assert!(String::from_utf8(compressed.stderr)
+        .unwrap()
+        .contains("ratio"));
+
+    let decompressed = run(&["decompress"], &compressed.stdout);
+    assert!(decompressed.status.success(), "{decompressed:?}");
+    assert_eq!(decompressed.stdout, data);
+
+    let gage = run(&["compress", "--method", "gage", "--quiet"], &data);
+    assert!(gage.status.success(), "{gage:?}");
+    assert!(gage.stderr.is_empty());
+    assert_eq!(run(&["decompress"], &gage.stdout).stdout, data);
+
+    let varints = run(&["compress", "--entropy", "none", "--quiet"], &data);
+    assert!(varints.stdout.len() > compressed.stdout.len());
+    assert_eq!(run(&["decompress"], &varints.stdout).stdout, data);
+
+    let corrupt = run(&["decompress"], CORPUS.as_bytes());
+    assert_eq!(corrupt.status.code(), Some(1));
+}
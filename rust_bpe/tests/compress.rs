@@ -0,0 +1,131 @@
+use rust_bpe::{decompress, Compressor, EntropyCoding, Error};
+
+fn random_bytes(len: usize, mut state: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn log_lines() -> Vec<u8> {
+    (0..400)
+        .map(|i| {
+            format!(
+                "2024-05-{:02} 12:{:02}:{:02} INFO worker-{} finished job {} in {} ms\n",
+                i % 28 + 1,
+                i % 60,
+                (i * 7) % 60,
+                i % 8,
+                1000 + i,
+                i * 13 % 997
+            )
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn round_trip(data: &[u8]) {
+    for entropy_coding in [EntropyCoding::None, EntropyCoding::Huffman] {
+        let compressed = Compressor::new()
+            .min_frequency(2)
+            .entropy_coding(entropy_coding)
+            .compress(data);
+        assert_eq!(compressed.input_len(), data.len());
+        assert_eq!(decompress(compressed.as_bytes()).unwrap(), data);
+    }
+}
+
+#[test]
+fn lossless_on_any_bytes() {
+    round_trip(b"");
+    round_trip(b" ");
+    round_trip(&log_lines());
+    round_trip(&random_bytes(10_000, 0x9e37_79b9_7f4a_7c15));
+    // Long runs of one byte are cut into words, and tokens stay short.
+    round_trip(&[0u8; 5000]);
+    round_trip(&(0..=255u8).cycle().take(20_000).collect::<Vec<_>>());
+    let mut invalid_utf8 = log_lines();
+    invalid_utf8.extend_from_slice(b"\xff\xfe\xc3 caf\xc3\xa9 \xe2\x82");
+    round_trip(&invalid_utf8);
+}
+
+#[test]
+fn compresses_text() {
+    let data = log_lines();
+    let compressed = Compressor::new().compress(&data);
+    assert!(compressed.merges() > 0);
+    assert!(compressed.tokens() < data.len() / 2);
+    assert!(compressed.ratio() > 2.0, "ratio {}", compressed.ratio());
+    assert_eq!(compressed.compressed_len(), compressed.as_bytes().len());
+}
+
+#[test]
+fn huffman_beats_varints() {
+    let data = log_lines();
+    let varints = Compressor::new()
+        .entropy_coding(EntropyCoding::None)
+        .compress(&data);
+    let huffman = Compressor::new().compress(&data);
+    assert_eq!(huffman.tokens(), varints.tokens());
+    assert!(huffman.ratio() > 1.3 * varints.ratio());
+}
+
+#[test]
+fn container_layout() {
+    let compressed = Compressor::new()
+        .entropy_coding(EntropyCoding::None)
+        .compress(b"ab")
+        .into_bytes();
+    // Magic, version, flags, length 2, no merges, the symbols `a` and `b`, two tokens.
+    assert_eq!(compressed, b"BPEZ\x01\x00\x02\x00\x02ab\x02\x00\x01");
+
+    let compressed = Compressor::new().compress(b"ab").into_bytes();
+    // The same up to the token count, then two codes of length 1 and the bits `01`.
+    assert_eq!(compressed, b"BPEZ\x01\x01\x02\x00\x02ab\x02\x01\x02\x40");
+}
+
+#[test]
+fn rejects_corrupt_data() {
+    let compressed = Compressor::new().compress(&log_lines()).into_bytes();
+    let varints = Compressor::new()
+        .entropy_coding(EntropyCoding::None)
+        .compress(&log_lines())
+        .into_bytes();
+    let invalid = |data: &[u8]| matches!(decompress(data), Err(Error::InvalidCompressedData(_)));
+
+    assert!(invalid(b""));
+    assert!(invalid(b"PK\x03\x04 not ours"));
+    assert!(invalid(b"BPEZ\x02\x00\x00\x00\x00"));
+    for compressed in [&compressed, &varints] {
+        assert!(invalid(&compressed[..compressed.len() - 1]));
+        let mut trailing = compressed.clone();
+        trailing.push(0);
+        assert!(invalid(&trailing));
+    }
+    // Unknown flags.
+    assert!(invalid(b"BPEZ\x01\x02\x00\x00\x00\x00"));
+    // Huffman coded `ab` with padding bits set.
+    assert!(invalid(b"BPEZ\x01\x01\x02\x00\x02ab\x02\x01\x02\x41"));
+    // Three codes of length 1.
+    assert!(invalid(b"BPEZ\x01\x01\x03\x00\x03abc\x03\x01\x03\x40"));
+    // A code of length 2 that is not used.
+    assert!(invalid(b"BPEZ\x01\x01\x01\x00\x01a\x01\x02\x00\x01\x40"));
+    // Length 1 but a two byte token stream.
+    assert!(invalid(b"BPEZ\x01\x00\x01\x00\x02ab\x02\x00\x01"));
+    // Token 256 without any merge.
+    assert!(invalid(b"BPEZ\x01\x00\x02\x00\x01\x80\x02\x01\x00"));
+    // Symbol 1 with a single entry in the symbol table.
+    assert!(invalid(b"BPEZ\x01\x00\x01\x00\x01a\x01\x01"));
+    // Merges doubling the token length every time.
+    let mut doubling = b"BPEZ\x01\x00\x00\x09aa".to_vec();
+    for id in 256u32..264 {
+        let varint = [(id as u8) | 0x80, (id >> 7) as u8];
+        doubling.extend_from_slice(&varint);
+        doubling.extend_from_slice(&varint);
+    }
+    assert!(invalid(&doubling));
+}
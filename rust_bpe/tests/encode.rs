@@ -0,0 +1,40 @@
+use rust_bpe::BPE;
+
+#[test]
+fn merges_apply_in_rank_order() {
+    let bpe = BPE::new();
+    // `bc` is learned before `ab`, so it wins in `abc` although `ab` comes first.
+    let vocabulary = bpe.build("bcbcbcab ab");
+    assert_eq!(vocabulary.token(256), Some(&b"bc"[..]));
+    assert_eq!(vocabulary.token(257), Some(&b"ab"[..]));
+    assert_eq!(bpe.encode("abc", &vocabulary), [97, 256]);
+    assert_eq!(bpe.encode("abab", &vocabulary), [257, 257]);
+}
+
+#[test]
+fn same_ids_for_text_and_bytes() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build("abababcab");
+    let text = String::from("xabab");
+    let ids = [120, 257];
+    assert_eq!(bpe.encode(&text, &vocabulary), ids);
+    assert_eq!(bpe.encode(text.as_str(), &vocabulary), ids);
+    assert_eq!(bpe.encode(text.as_bytes(), &vocabulary), ids);
+    assert_eq!(bpe.encode(b"xabab", &vocabulary), ids);
+    assert!(bpe.encode("", &vocabulary).is_empty());
+}
+
+#[test]
+fn ids_are_stable() {
+    // Fixed ids rather than a comparison of two runs, so that a dependence on hash order shows
+    // up as well.
+    let bpe = BPE::new();
+    let vocabulary = bpe.build("the cat and the hat and the bat");
+    let ids = bpe.encode("the hat and the cat", &vocabulary);
+    assert_eq!(ids, [259, 104, 265, 99, 256]);
+    let tokens: Vec<_> = ids
+        .iter()
+        .map(|&id| vocabulary.token(id).unwrap())
+        .collect();
+    assert_eq!(tokens, [&b"the "[..], b"h", b"at and the ", b"c", b"at"]);
+}
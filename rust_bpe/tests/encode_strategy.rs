@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use rust_bpe::{BpeTrainer, EncodeStrategy, MergeMode, RegexPreTokenizer, Vocabulary, BPE};
+
+fn random_text(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Mostly a small alphabet so that long merges form, now and then any byte.
+            match state % 16 {
+                0 => (state >> 8) as u8,
+                _ => b"aab ba"[((state >> 8) % 6) as usize],
+            }
+        })
+        .collect()
+}
+
+fn assert_strategies_agree(vocabulary: &Vocabulary, inputs: &[Vec<u8>]) {
+    let reference = BPE::new();
+    let mut heap = BPE::new();
+    heap.set_encode_strategy(EncodeStrategy::Heap);
+    for input in inputs {
+        let expected = reference.encode(input, vocabulary);
+        assert_eq!(heap.encode(input, vocabulary), expected);
+        assert_eq!(heap.decode(&expected, vocabulary).unwrap(), *input);
+    }
+}
+
+fn inputs() -> Vec<Vec<u8>> {
+    let mut inputs: Vec<_> = (1..200)
+        .map(|seed| random_text(seed, seed as usize))
+        .collect();
+    inputs.push(random_text(7, 2_000));
+    inputs.push(b"a".repeat(1_000));
+    inputs.push(Vec::new());
+    inputs
+}
+
+#[test]
+fn merges_mode() {
+    let corpus = random_text(42, 50_000);
+    let bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1).max_merges(300));
+    let vocabulary = bpe.build_from_iter([&corpus]);
+    assert_eq!(vocabulary.merge_mode(), MergeMode::Merges);
+    assert!(vocabulary.merges().len() > 100);
+
+    assert_strategies_agree(&vocabulary, &inputs());
+}
+
+#[test]
+fn ranks_mode() {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gpt2-small/ranks.tiktoken");
+    let mut vocabulary = Vocabulary::from_tiktoken_file(path).unwrap();
+    assert_eq!(vocabulary.merge_mode(), MergeMode::Ranks);
+
+    let mut inputs = inputs();
+    inputs.push(b"the quick brown fox jumps over the lazy dog ".repeat(40));
+    assert_strategies_agree(&vocabulary, &inputs);
+    vocabulary.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    assert_strategies_agree(&vocabulary, &inputs);
+}
+
+#[test]
+fn default_strategy() {
+    assert_eq!(BPE::new().encode_strategy(), EncodeStrategy::Reference);
+}
@@ -0,0 +1,127 @@
+"""Generates the gpt2-small fixtures.
+
+Trains a tiny byte-level BPE on the corpus below and writes it in the Hugging Face and GPT-2
+formats, with golden encodings produced by the Hugging Face `tokenizers` library from the
+written `tokenizer.json`.
+
+`golden_added_tokens.json` holds two non-special added tokens and the encodings `tokenizers`
+produces once they are added to `tokenizer.json`.
+
+The same tokens are also written as a tiktoken rank file, with golden encodings produced by
+`tiktoken` from those ranks.
+
+Usage: python3 generate.py [OUT_DIR]   (needs the `regex`, `tokenizers` and `tiktoken` packages)
+"""
+import base64, copy, json, regex, sys, os
+import tiktoken, tokenizers
+from collections import Counter
+
+def bytes_to_unicode():
+    bs = list(range(ord("!"), ord("~")+1))+list(range(ord("¡"), ord("¬")+1))+list(range(ord("®"), ord("ÿ")+1))
+    cs = bs[:]
+    n = 0
+    for b in range(2**8):
+        if b not in bs:
+            bs.append(b); cs.append(2**8+n); n += 1
+    return dict(zip(bs, [chr(c) for c in cs]))
+
+pat = regex.compile(r"""'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+""")
+b2u = bytes_to_unicode()
+b2u_inv = {c: b for b, c in b2u.items()}
+
+corpus = """The quick brown fox jumps over the lazy dog. The dog doesn't care, it's sleeping.
+They're saying we've seen 1234 foxes and 5678 dogs in 2023, but I'll count again.
+    Indented lines,   multiple   spaces, and tabs\tare common in source code!
+def encode(self, text): return [self.encoder[token] for token in text.split()]
+Über naïve café résumé — “quotes” and ‘apostrophes’… 日本語のテキスト 🦊🦊
+the the the then there these those other mother brother further together
+"""
+
+words = Counter()
+for w in pat.findall(corpus):
+    words[tuple(b2u[b] for b in w.encode('utf-8'))] += 1
+
+vocab = list(b2u.values())
+merges = []
+words = {w: c for w, c in words.items()}
+while True:
+    pairs = Counter()
+    for w, c in words.items():
+        for a, b in zip(w, w[1:]):
+            pairs[(a, b)] += c
+    if not pairs: break
+    best = max(pairs.items(), key=lambda kv: (kv[1], [-ord(ch) for ch in kv[0][0] + ' ' + kv[0][1]]))
+    (a, b), count = best
+    if count < 2: break
+    merges.append((a, b))
+    new = a + b
+    if new not in vocab: vocab.append(new)
+    nw = {}
+    for w, c in words.items():
+        out = []; i = 0
+        while i < len(w):
+            if i + 1 < len(w) and w[i] == a and w[i+1] == b:
+                out.append(new); i += 2
+            else:
+                out.append(w[i]); i += 1
+        nw[tuple(out)] = nw.get(tuple(out), 0) + c
+    words = nw
+vocab.append("<|endoftext|>")
+encoder = {t: i for i, t in enumerate(vocab)}
+mergeable_ranks = {bytes(b2u_inv[c] for c in t): i for t, i in encoder.items() if t != "<|endoftext|>"}
+
+texts = [
+    "The quick brown fox jumps over the lazy dog.",
+    "They're saying it's 2024 and we'll see 99 foxes; I'd count them.",
+    "  leading spaces,   inner   runs and trailing spaces   ",
+    "tabs\tand\nnewlines\n\n  mixed \t whitespace",
+    "def encode(self, text): return self.encoder[text]",
+    "Über naïve café — “résumé” 日本語 🦊 emoji",
+    "the other brother went further together with their mother",
+    "",
+    "x",
+]
+out = sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__))
+os.makedirs(out, exist_ok=True)
+with open(f"{out}/ranks.tiktoken", "w") as f:
+    for token, rank in sorted(mergeable_ranks.items(), key=lambda kv: kv[1]):
+        f.write(f"{base64.b64encode(token).decode()} {rank}\n")
+json.dump(encoder, open(f"{out}/vocab.json", "w"), ensure_ascii=False)
+with open(f"{out}/merges.txt", "w") as f:
+    f.write("#version: 0.2\n")
+    for a, b in merges:
+        f.write(f"{a} {b}\n")
+tok = {
+  "version": "1.0",
+  "truncation": None,
+  "padding": None,
+  "added_tokens": [{"id": encoder["<|endoftext|>"], "content": "<|endoftext|>", "single_word": False, "lstrip": False, "rstrip": False, "normalized": True, "special": True}],
+  "normalizer": None,
+  "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": False, "trim_offsets": True, "use_regex": True},
+  "post_processor": {"type": "ByteLevel", "add_prefix_space": True, "trim_offsets": False, "use_regex": True},
+  "decoder": {"type": "ByteLevel", "add_prefix_space": True, "trim_offsets": True, "use_regex": True},
+  "model": {"type": "BPE", "dropout": None, "unk_token": None, "continuing_subword_prefix": "", "end_of_word_suffix": "", "fuse_unk": False, "byte_fallback": False, "vocab": encoder, "merges": [f"{a} {b}" for a, b in merges]},
+}
+json.dump(tok, open(f"{out}/tokenizer.json", "w"), ensure_ascii=False, indent=2)
+
+hf = tokenizers.Tokenizer.from_file(f"{out}/tokenizer.json")
+golden = [{"text": t, "ids": hf.encode(t).ids} for t in texts]
+json.dump(golden, open(f"{out}/golden.json", "w"), ensure_ascii=False, indent=2)
+enc = tiktoken.Encoding("gpt2-small", pat_str=pat.pattern, mergeable_ranks=mergeable_ranks, special_tokens={})
+golden = [{"text": t, "ids": enc.encode_ordinary(t)} for t in texts]
+json.dump(golden, open(f"{out}/golden_tiktoken.json", "w"), ensure_ascii=False, indent=2)
+
+added = [
+    {"id": len(encoder), "content": "foxes", "single_word": False, "lstrip": False, "rstrip": False, "normalized": True, "special": False},
+    {"id": encoder["other"], "content": "other", "single_word": False, "lstrip": False, "rstrip": False, "normalized": True, "special": False},
+]
+added_texts = [
+    "the other foxes jumped over my foxes' other dog",
+    "foxesfoxes brother<|endoftext|>others",
+    "no added tokens here",
+]
+tok_added = copy.deepcopy(tok)
+tok_added["added_tokens"] += added
+hf = tokenizers.Tokenizer.from_str(json.dumps(tok_added))
+golden = {"added_tokens": added, "cases": [{"text": t, "ids": hf.encode(t).ids} for t in added_texts]}
+json.dump(golden, open(f"{out}/golden_added_tokens.json", "w"), ensure_ascii=False, indent=2)
@@ -0,0 +1,110 @@
+use rust_bpe::{decompress, Compressor, Error, GageCompressor};
+
+fn random_bytes(len: usize, mut state: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn log_lines() -> Vec<u8> {
+    (0..400)
+        .map(|i| {
+            format!(
+                "2024-05-{:02} 12:{:02}:{:02} INFO worker-{} finished job {} in {} ms\n",
+                i % 28 + 1,
+                i % 60,
+                (i * 7) % 60,
+                i % 8,
+                1000 + i,
+                i * 13 % 997
+            )
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn round_trip(compressor: &GageCompressor, data: &[u8]) {
+    let compressed = compressor.compress(data);
+    assert_eq!(compressed.input_len(), data.len());
+    assert_eq!(decompress(compressed.as_bytes()).unwrap(), data);
+}
+
+#[test]
+fn lossless_on_any_bytes() {
+    let compressor = GageCompressor::new();
+    round_trip(&compressor, b"");
+    round_trip(&compressor, b"x");
+    round_trip(&compressor, &log_lines());
+    round_trip(&compressor, &random_bytes(20_000, 0x9e37_79b9_7f4a_7c15));
+    round_trip(&compressor, &[7u8; 10_000]);
+    // Every byte value occurs, so blocks are cut to leave values for pairs.
+    let all_bytes: Vec<u8> = (0..=255u8).cycle().take(20_000).collect();
+    round_trip(&compressor, &all_bytes);
+    round_trip(&GageCompressor::new().block_size(1), &log_lines()[..500]);
+    assert_eq!(
+        GageCompressor::new().block_size(0),
+        GageCompressor::new().block_size(1)
+    );
+    round_trip(&GageCompressor::new().block_size(100_000), &log_lines());
+    round_trip(&GageCompressor::new().min_frequency(0), b"abcabc");
+}
+
+#[test]
+fn pair_table_layout() {
+    let compressed = GageCompressor::new().compress(b"abababab").into_bytes();
+    // `ab` becomes 0 and `00` becomes 1. The table lists the pairs at 0 and 1, skips 127
+    // values, lists 129 as standing for itself and skips the remaining 126.
+    assert_eq!(
+        compressed,
+        b"BPEG\x01\x08\x01ab\x00\x00\xfe\x81\xfd\x02\x01\x01"
+    );
+}
+
+#[test]
+fn compresses_logs() {
+    let data = log_lines();
+    let gage = GageCompressor::new().compress(&data);
+    assert!(gage.merges() > 0);
+    assert!(gage.ratio() > 2.0, "ratio {}", gage.ratio());
+
+    // Both compressors are read by the same function.
+    let tokens = Compressor::new().compress(&data);
+    assert_eq!(decompress(tokens.as_bytes()).unwrap(), data);
+}
+
+#[test]
+fn rejects_corrupt_data() {
+    let compressed = GageCompressor::new().compress(&log_lines()).into_bytes();
+    let invalid = |data: &[u8]| matches!(decompress(data), Err(Error::InvalidCompressedData(_)));
+
+    assert!(invalid(&compressed[..compressed.len() - 1]));
+    assert!(invalid(b"BPEG\x02\x00"));
+    // A table without pairs, stored length 3 but one byte of data.
+    assert!(invalid(b"BPEG\x01\x03\xfe\x7f\xfe\xff\x01x"));
+    // A stored length far beyond what the blocks hold.
+    assert!(invalid(
+        b"BPEG\x01\xff\xff\xff\xff\xff\xff\xff\x0f\xfe\x7f\xfe\xff\x01x"
+    ));
+    // A run of pairs past byte 255.
+    assert!(invalid(b"BPEG\x01\x01\xfe\x7f\xfd\xfe\x01"));
+    // 0 stands for 1 followed by 0, and 1 for 0 followed by 1.
+    assert!(invalid(
+        b"BPEG\x01\x01\x01\x01\x00\x00\x01\xfe\x81\xfd\x01\x00"
+    ));
+}
+
+#[test]
+fn ties_go_to_the_first_pair() {
+    // `ab`, `bc` and `cd` all occur 3 times, `ab` becomes 0, then `0c` becomes 1 and `1d` 2.
+    let compressed = GageCompressor::new().compress(b"abcdabcdabcd").into_bytes();
+    assert_eq!(
+        compressed,
+        b"BPEG\x01\x0c\x02ab\x00c\x01d\xfe\x82\xfc\x03\x02\x02\x02"
+    );
+    assert_eq!(decompress(&compressed).unwrap(), b"abcdabcdabcd");
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use rust_bpe::{Vocabulary, BPE};
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/gpt2-small")
+        .join(name)
+}
+
+fn load() -> Vocabulary {
+    Vocabulary::from_gpt2_files(fixture("vocab.json"), fixture("merges.txt")).unwrap()
+}
+
+#[test]
+fn golden_encodings() {
+    let golden: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("golden.json")).unwrap()).unwrap();
+    let vocabulary = load();
+    let bpe = BPE::new();
+    for case in golden.as_array().unwrap() {
+        let text = case["text"].as_str().unwrap();
+        let ids: Vec<u32> = serde_json::from_value(case["ids"].clone()).unwrap();
+        assert_eq!(bpe.encode(text, &vocabulary), ids, "{text:?}");
+        assert_eq!(bpe.decode_to_string(&ids, &vocabulary).unwrap(), text);
+    }
+}
+
+/// The fixtures are a tiny synthetic model, so this checks the real GPT-2 ids as well, from the
+/// `vocab.json` and `merges.txt` of the released model in `RUST_BPE_GPT2_DIR`.
+#[test]
+#[ignore = "needs the GPT-2 vocab.json and merges.txt in RUST_BPE_GPT2_DIR"]
+fn released_gpt2_encodings() {
+    let dir = PathBuf::from(std::env::var_os("RUST_BPE_GPT2_DIR").expect("RUST_BPE_GPT2_DIR"));
+    let vocabulary =
+        Vocabulary::from_gpt2_files(dir.join("vocab.json"), dir.join("merges.txt")).unwrap();
+    assert_eq!(vocabulary.len(), 50257);
+    let bpe = BPE::new();
+    for (text, ids) in [
+        ("Hello world", &[15496, 995][..]),
+        ("Hello, world!", &[15496, 11, 995, 0]),
+        ("The quick brown fox", &[464, 2068, 7586, 21831]),
+    ] {
+        assert_eq!(bpe.encode(text, &vocabulary), ids, "{text:?}");
+        assert_eq!(bpe.decode_to_string(ids, &vocabulary).unwrap(), text);
+    }
+}
+
+#[test]
+fn same_model_as_tokenizer_json() {
+    let hf = Vocabulary::load_hf_tokenizer(fixture("tokenizer.json")).unwrap();
+    assert_eq!(load(), hf);
+}
+
+#[test]
+fn write_and_read_back() {
+    let vocabulary = load();
+    let dir = std::env::temp_dir().join(format!("rust_bpe_gpt2_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (vocab_path, merges_path) = (dir.join("vocab.json"), dir.join("merges.txt"));
+    vocabulary
+        .save_gpt2_files(&vocab_path, &merges_path)
+        .unwrap();
+
+    let merges = std::fs::read_to_string(&merges_path).unwrap();
+    let reloaded = Vocabulary::from_gpt2_files(&vocab_path, &merges_path);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(merges.starts_with("#version: 0.2\nh e\n"));
+    assert_eq!(reloaded.unwrap(), vocabulary);
+}
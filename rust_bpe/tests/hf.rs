@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+
+use rust_bpe::{
+    AllowedSpecial, BpeTrainer, Error, PreTokenizer, RegexPreTokenizer, Vocabulary, BPE,
+};
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/gpt2-small")
+        .join(name)
+}
+
+fn golden() -> Vec<(String, Vec<u32>)> {
+    let golden: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("golden.json")).unwrap()).unwrap();
+    golden
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|case| {
+            let ids = case["ids"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|id| id.as_u64().unwrap() as u32);
+            (case["text"].as_str().unwrap().to_string(), ids.collect())
+        })
+        .collect()
+}
+
+#[test]
+fn matches_reference_encodings() {
+    let vocabulary = Vocabulary::load_hf_tokenizer(fixture("tokenizer.json")).unwrap();
+    assert!(!vocabulary.add_prefix_space());
+    assert_eq!(
+        vocabulary.pre_tokenizer().and_then(PreTokenizer::to_json),
+        RegexPreTokenizer::gpt2().to_json()
+    );
+    assert_eq!(vocabulary.special_tokens(), [315]);
+
+    let bpe = BPE::new();
+    for (text, ids) in golden() {
+        assert_eq!(bpe.encode(&text, &vocabulary), ids, "{text:?}");
+        assert_eq!(bpe.decode_to_string(&ids, &vocabulary).unwrap(), text);
+    }
+}
+
+#[test]
+fn add_prefix_space() {
+    let mut json: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("tokenizer.json")).unwrap()).unwrap();
+    json["pre_tokenizer"]["add_prefix_space"] = true.into();
+    let prefixed = Vocabulary::from_hf_tokenizer_json(&json).unwrap();
+    let plain = Vocabulary::load_hf_tokenizer(fixture("tokenizer.json")).unwrap();
+
+    let bpe = BPE::new();
+    assert_eq!(
+        bpe.encode("the fox", &prefixed),
+        bpe.encode(" the fox", &plain)
+    );
+    assert_eq!(
+        bpe.encode(" the fox", &prefixed),
+        bpe.encode(" the fox", &plain)
+    );
+    assert_eq!(bpe.encode("", &prefixed), Vec::<u32>::new());
+}
+
+#[test]
+fn export_roundtrip() {
+    let imported = Vocabulary::load_hf_tokenizer(fixture("tokenizer.json")).unwrap();
+    assert_eq!(
+        Vocabulary::from_hf_tokenizer_json(&imported.to_hf_tokenizer_json()).unwrap(),
+        imported
+    );
+
+    let trainer = BpeTrainer::new().special_tokens(["<|endoftext|>"]);
+    let trained =
+        BPE::with_trainer(trainer).build("hello hello world, hello there \u{1f98a}\u{1f98a}");
+    let exported = trained.to_hf_tokenizer_json();
+    assert_eq!(exported["model"]["merges"][0], "h e");
+    assert_eq!(exported["added_tokens"][0]["content"], "<|endoftext|>");
+    assert_eq!(
+        Vocabulary::from_hf_tokenizer_json(&exported).unwrap(),
+        trained
+    );
+}
+
+#[test]
+fn rejects_unsupported_models() {
+    let json: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("tokenizer.json")).unwrap()).unwrap();
+
+    let mut wordpiece = json.clone();
+    wordpiece["model"]["type"] = "WordPiece".into();
+    assert!(matches!(
+        Vocabulary::from_hf_tokenizer_json(&wordpiece),
+        Err(Error::Unsupported(_))
+    ));
+
+    let mut suffix = json.clone();
+    suffix["model"]["end_of_word_suffix"] = "</w>".into();
+    assert!(matches!(
+        Vocabulary::from_hf_tokenizer_json(&suffix),
+        Err(Error::Unsupported(_))
+    ));
+
+    let mut broken = json;
+    broken["model"]["merges"][0] = "not_a_token e".into();
+    assert!(matches!(
+        Vocabulary::from_hf_tokenizer_json(&broken),
+        Err(Error::InvalidVocabulary(_))
+    ));
+}
+
+#[test]
+fn rejects_ids_out_of_range() {
+    let json: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("tokenizer.json")).unwrap()).unwrap();
+
+    let mut huge = json.clone();
+    huge["model"]["vocab"]["a"] = 4_000_000_000u32.into();
+    assert!(matches!(
+        Vocabulary::from_hf_tokenizer_json(&huge),
+        Err(Error::InvalidVocabulary(_))
+    ));
+
+    let mut huge = json;
+    huge["added_tokens"][0]["id"] = 4_000_000_000u32.into();
+    assert!(matches!(
+        Vocabulary::from_hf_tokenizer_json(&huge),
+        Err(Error::InvalidVocabulary(_))
+    ));
+}
+
+#[test]
+fn added_tokens_honor_the_special_flag() {
+    let mut json: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("tokenizer.json")).unwrap()).unwrap();
+    let id = json["added_tokens"][0]["id"].as_u64().unwrap() as u32;
+    let special = Vocabulary::from_hf_tokenizer_json(&json).unwrap();
+    assert_eq!(special.special_tokens(), [id]);
+
+    json["added_tokens"][0]["special"] = false.into();
+    let plain = Vocabulary::from_hf_tokenizer_json(&json).unwrap();
+    assert!(plain.special_tokens().is_empty());
+    assert_eq!(plain.token(id), Some(&b"<|endoftext|>"[..]));
+}
+
+#[test]
+fn non_special_added_tokens_match_reference_encodings() {
+    let mut json: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("tokenizer.json")).unwrap()).unwrap();
+    let golden: Value = serde_json::from_str(
+        &std::fs::read_to_string(fixture("golden_added_tokens.json")).unwrap(),
+    )
+    .unwrap();
+    for token in golden["added_tokens"].as_array().unwrap() {
+        json["added_tokens"]
+            .as_array_mut()
+            .unwrap()
+            .push(token.clone());
+    }
+    let vocabulary = Vocabulary::from_hf_tokenizer_json(&json).unwrap();
+    assert_eq!(vocabulary.added_tokens(), [278, 316]);
+    assert_eq!(vocabulary.special_tokens(), [315]);
+
+    let mut bpe = BPE::new();
+    bpe.set_allowed_special(AllowedSpecial::All);
+    for case in golden["cases"].as_array().unwrap() {
+        let text = case["text"].as_str().unwrap();
+        let ids: Vec<u32> = case["ids"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_u64().unwrap() as u32)
+            .collect();
+        assert_eq!(bpe.encode(text, &vocabulary), ids, "{text:?}");
+        assert_eq!(bpe.decode_to_string(&ids, &vocabulary).unwrap(), text);
+    }
+
+    // Added tokens are matched whatever special tokens are allowed, and kept when skipping them.
+    let mut plain = BPE::new();
+    let ids = plain.encode("foxes<|endoftext|>", &vocabulary);
+    assert_eq!(ids[0], 316);
+    assert!(!ids.contains(&315));
+    plain.set_skip_special_tokens(true);
+    assert_eq!(
+        plain
+            .decode_to_string(&[316, 315, 278], &vocabulary)
+            .unwrap(),
+        "foxesother"
+    );
+
+    assert_eq!(
+        Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap(),
+        vocabulary
+    );
+    let exported = vocabulary.to_hf_tokenizer_json();
+    assert_eq!(exported["added_tokens"][0]["content"], "other");
+    assert_eq!(exported["added_tokens"][0]["special"], false);
+    assert_eq!(
+        Vocabulary::from_hf_tokenizer_json(&exported).unwrap(),
+        vocabulary
+    );
+}
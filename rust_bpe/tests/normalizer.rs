@@ -0,0 +1,71 @@
+use rust_bpe::{Error, Normalizer, Vocabulary, BPE};
+use serde_json::json;
+
+#[test]
+fn forms() {
+    let composed = "\u{e9}";
+    let decomposed = "e\u{301}";
+    assert_eq!(Normalizer::Nfc.normalize(decomposed), composed);
+    assert_eq!(Normalizer::Nfd.normalize(composed), decomposed);
+    assert_eq!(Normalizer::Nfkc.normalize("\u{fb01}x\u{b2}"), "fix2");
+    assert_eq!(Normalizer::Nfkd.normalize("\u{fb01}\u{e9}"), "fie\u{301}");
+    assert_eq!(Normalizer::Lowercase.normalize("ÀB\u{130}"), "àbi\u{307}");
+    assert_eq!(Normalizer::StripAccents.normalize(decomposed), "e");
+    // Only nonspacing marks are removed, composed characters are left alone.
+    assert_eq!(Normalizer::StripAccents.normalize(composed), composed);
+    assert_eq!(Normalizer::Sequence(Vec::new()).normalize("Ab"), "Ab");
+}
+
+#[test]
+fn json_form() {
+    let normalizer = Normalizer::Sequence(vec![
+        Normalizer::Nfkd,
+        Normalizer::StripAccents,
+        Normalizer::Lowercase,
+    ]);
+    let json = normalizer.to_json();
+    assert_eq!(
+        json,
+        json!({
+            "type": "Sequence",
+            "normalizers": [{ "type": "NFKD" }, { "type": "StripAccents" }, { "type": "Lowercase" }],
+        })
+    );
+    assert_eq!(Normalizer::from_json(&json).unwrap(), normalizer);
+    assert!(matches!(
+        Normalizer::from_json(&json!({ "type": "BertNormalizer" })),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn training_and_encoding_share_the_normalization() {
+    let mixed = "caf\u{e9} cafe\u{301} CAF\u{c9} cafe\u{301}";
+    let mut bpe = BPE::new();
+    bpe.set_normalizer(Normalizer::Sequence(vec![
+        Normalizer::Nfc,
+        Normalizer::Lowercase,
+    ]));
+    let vocabulary = bpe.build(mixed);
+    assert_eq!(
+        vocabulary,
+        bpe.build("caf\u{e9} caf\u{e9} caf\u{e9} caf\u{e9}")
+    );
+
+    let expected = bpe.encode("caf\u{e9}", &vocabulary);
+    assert_eq!(expected.len(), 1);
+    for spelling in ["cafe\u{301}", "CAF\u{c9}", "CAFE\u{301}"] {
+        assert_eq!(bpe.encode(spelling, &vocabulary), expected, "{spelling:?}");
+    }
+    // Invalid UTF-8 is kept while the text around it is normalized.
+    let tokens = bpe.encode(b"\xffCAF\xc3\x89", &vocabulary);
+    assert_eq!(
+        bpe.decode(&tokens, &vocabulary).unwrap(),
+        b"\xffcaf\xc3\xa9"
+    );
+
+    let reloaded = Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded.normalizer(), bpe.normalizer());
+    let hf = Vocabulary::from_hf_tokenizer_json(&vocabulary.to_hf_tokenizer_json()).unwrap();
+    assert_eq!(hf, vocabulary);
+}
@@ -0,0 +1,152 @@
+use rust_bpe::{AllowedSpecial, BpeTrainer, Normalizer, RegexPreTokenizer, Vocabulary, BPE};
+
+const CORPUS: &str = "The café serves crème brûlée.<|endoftext|>ΟΔΟΣ and ﬁne ﬁsh in İstanbul.";
+
+fn build(normalizer: Option<Normalizer>) -> (BPE, Vocabulary) {
+    let trainer = BpeTrainer::new()
+        .min_frequency(1)
+        .special_tokens(["<|endoftext|>"]);
+    let mut bpe = BPE::with_trainer(trainer);
+    if let Some(normalizer) = normalizer {
+        bpe.set_normalizer(normalizer);
+    }
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    bpe.set_allowed_special(AllowedSpecial::All);
+    let vocabulary = bpe.build(&CORPUS.repeat(3));
+    (bpe, vocabulary)
+}
+
+fn inputs() -> Vec<&'static str> {
+    vec![
+        CORPUS,
+        "",
+        "Cafe\u{301} CRÈME",
+        "<|endoftext|><|endoftext|>x",
+        "ΌΣΟΣ ΣΑΣ",
+        "ﬁﬁ İİ \u{1100}\u{1161}\u{11a8}",
+    ]
+}
+
+#[test]
+fn ids_match_encode() {
+    let normalizers = [
+        None,
+        Some(Normalizer::Nfc),
+        Some(Normalizer::Nfkc),
+        Some(Normalizer::Lowercase),
+        Some(Normalizer::Sequence(vec![
+            Normalizer::Nfd,
+            Normalizer::StripAccents,
+            Normalizer::Lowercase,
+        ])),
+    ];
+    for normalizer in normalizers {
+        let (bpe, mut vocabulary) = build(normalizer.clone());
+        for add_prefix_space in [false, true] {
+            vocabulary.set_add_prefix_space(add_prefix_space);
+            for input in inputs() {
+                let encoding = bpe.encode_with_offsets(input, &vocabulary);
+                assert_eq!(
+                    encoding.ids(),
+                    bpe.encode(input, &vocabulary),
+                    "{normalizer:?} {input:?}"
+                );
+                assert_eq!(encoding.tokens().len(), encoding.len());
+                // Byte tokens may start within a character, but never beyond the input.
+                for range in encoding.offsets() {
+                    assert!(range.start <= range.end && range.end <= input.len());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn offsets_without_normalizer() {
+    let (bpe, vocabulary) = build(None);
+    let input = "crème<|endoftext|> brûlée";
+    let encoding = bpe.encode_with_offsets(input, &vocabulary);
+    for (range, id) in encoding.offsets().iter().zip(encoding.ids()) {
+        assert_eq!(
+            input.as_bytes()[range.clone()],
+            *vocabulary.token(*id).unwrap()
+        );
+    }
+    assert_eq!(encoding.word_ids().first(), Some(&Some(0)));
+    let special = encoding
+        .word_ids()
+        .iter()
+        .position(Option::is_none)
+        .unwrap();
+    assert_eq!(encoding.tokens()[special], "<|endoftext|>");
+    assert_eq!(encoding.offsets()[special], 6..19);
+    assert_eq!(encoding.char_offsets()[special], 5..18);
+    // Word ids continue after a special token.
+    assert_eq!(encoding.word_ids()[special + 1], Some(1));
+    assert_eq!(encoding.char_offsets().last().unwrap().end, 25);
+}
+
+#[test]
+fn offsets_through_normalization() {
+    let (bpe, vocabulary) = build(Some(Normalizer::Nfkc));
+    // Two code points are composed into one, one ligature expands into two letters.
+    let input = "cafe\u{301} ﬁne";
+    let encoding = bpe.encode_with_offsets(input, &vocabulary);
+    // No token boundary falls between the `e` and the accent composed with it.
+    for range in encoding.offsets() {
+        assert!(
+            !range.is_empty() && range.start != 4 && range.end != 4,
+            "{range:?}"
+        );
+    }
+    // The piece of the last word includes the space in front of it.
+    assert_eq!(encoding.offsets().last(), Some(&(6..12)));
+    assert_eq!(encoding.char_offsets().last(), Some(&(5..9)));
+
+    // The final sigma depends on the following character, so the letters stay together.
+    let (bpe, vocabulary) = build(Some(Normalizer::Lowercase));
+    let encoding = bpe.encode_with_offsets("ΟΔΟΣ", &vocabulary);
+    assert_eq!(
+        bpe.decode(encoding.ids(), &vocabulary).unwrap(),
+        "οδος".as_bytes()
+    );
+    assert_eq!(encoding.char_offsets().last().unwrap().end, 4);
+}
+
+#[test]
+fn prefix_space_and_invalid_bytes() {
+    let (bpe, mut vocabulary) = build(None);
+    vocabulary.set_add_prefix_space(true);
+    let encoding = bpe.encode_with_offsets(b"The\xff\xfe!", &vocabulary);
+    assert_eq!(encoding.offsets()[0].start, 0);
+    // Both invalid bytes become a replacement character of their own.
+    assert_eq!(encoding.char_offsets().last().unwrap().end, 6);
+    assert!(encoding
+        .tokens()
+        .iter()
+        .any(|token| token.contains('\u{fffd}')));
+}
+
+#[test]
+fn long_runs_without_boundaries() {
+    // Every join of the final sigma changes the result, and a long run of combining marks has
+    // no starter to cut before.
+    for (normalizer, input) in [
+        (Normalizer::Lowercase, "Σ".repeat(50_000)),
+        (Normalizer::Nfc, format!("a{}", "\u{301}".repeat(50_000))),
+        (Normalizer::Nfc, "\u{212b}".repeat(50_000)),
+        (
+            Normalizer::Sequence(vec![Normalizer::Nfd, Normalizer::Lowercase]),
+            "ΣÉ".repeat(25_000),
+        ),
+    ] {
+        let (bpe, vocabulary) = build(Some(normalizer.clone()));
+        let encoding = bpe.encode_with_offsets(&input, &vocabulary);
+        assert_eq!(
+            encoding.ids(),
+            bpe.encode(&input, &vocabulary),
+            "{normalizer:?}"
+        );
+        assert_eq!(encoding.offsets().last().unwrap().end, input.len());
+    }
+}
@@ -0,0 +1,110 @@
+use rust_bpe::{Padding, PaddingLength, Side, Truncation, TruncationStrategy, Vocabulary, BPE};
+
+/// A vocabulary without merges, so every byte is a token.
+fn setup() -> (BPE, Vocabulary, u32) {
+    let bpe = BPE::new();
+    let mut vocabulary = bpe.build("");
+    let pad = vocabulary.add_special_token("<pad>");
+    (bpe, vocabulary, pad)
+}
+
+fn text(encoding: &rust_bpe::Encoding) -> String {
+    encoding.tokens().concat()
+}
+
+#[test]
+fn truncation_sides_and_stride() {
+    let (mut bpe, vocabulary, _) = setup();
+    bpe.set_truncation(Truncation::new(4));
+    let encoding = bpe.encode_with_offsets("abcdefghij", &vocabulary);
+    assert_eq!(text(&encoding), "abcd");
+    let windows: Vec<_> = encoding.overflowing().iter().map(text).collect();
+    assert_eq!(windows, ["efgh", "ij"]);
+    assert_eq!(encoding.overflowing()[1].offsets(), [8..9, 9..10]);
+
+    bpe.set_truncation(Truncation::new(4).stride(2).side(Side::Left));
+    let encoding = bpe.encode_with_offsets("abcdefghij", &vocabulary);
+    assert_eq!(text(&encoding), "ghij");
+    let windows: Vec<_> = encoding.overflowing().iter().map(text).collect();
+    assert_eq!(windows, ["efgh", "cdef", "abcd"]);
+
+    // A stride as long as the window still makes progress.
+    bpe.set_truncation(Truncation::new(3).stride(10));
+    let encoding = bpe.encode_with_offsets("abcde", &vocabulary);
+    let windows: Vec<_> = encoding.overflowing().iter().map(text).collect();
+    assert_eq!(windows, ["bcd", "cde"]);
+
+    bpe.set_truncation(Truncation::new(10));
+    let encoding = bpe.encode_with_offsets("abc", &vocabulary);
+    assert_eq!(text(&encoding), "abc");
+    assert!(encoding.overflowing().is_empty());
+}
+
+#[test]
+fn pair_strategies() {
+    let (mut bpe, vocabulary, _) = setup();
+    let pair = |bpe: &BPE| text(&bpe.encode_pair_with_offsets("abcdef", "xyz", &vocabulary));
+
+    bpe.set_truncation(Truncation::new(7));
+    assert_eq!(pair(&bpe), "abcdxyz");
+    bpe.set_truncation(Truncation::new(5));
+    assert_eq!(pair(&bpe), "abcxy");
+    bpe.set_truncation(Truncation::new(5).strategy(TruncationStrategy::OnlyFirst));
+    assert_eq!(pair(&bpe), "abxyz");
+    bpe.set_truncation(Truncation::new(7).strategy(TruncationStrategy::OnlySecond));
+    assert_eq!(pair(&bpe), "abcdefx");
+    bpe.remove_truncation();
+    assert_eq!(pair(&bpe), "abcdefxyz");
+}
+
+#[test]
+fn padding_to_longest_and_fixed() {
+    let (mut bpe, vocabulary, pad) = setup();
+    bpe.set_padding(Padding::new(pad));
+    let batch = bpe.encode_batch_with_offsets(&["abc", "a", ""], &vocabulary);
+    for encoding in &batch {
+        assert_eq!(encoding.len(), 3);
+    }
+    assert_eq!(batch[1].ids(), [97, pad, pad]);
+    assert_eq!(batch[1].tokens(), ["a", "<pad>", "<pad>"]);
+    assert_eq!(batch[1].attention_mask(), [1, 0, 0]);
+    assert_eq!(batch[1].word_ids(), [Some(0), None, None]);
+    assert_eq!(batch[2].attention_mask(), [0, 0, 0]);
+
+    bpe.set_padding(
+        Padding::new(pad)
+            .length(PaddingLength::Fixed(5))
+            .side(Side::Left),
+    );
+    let encoding = bpe.encode_with_offsets("ab", &vocabulary);
+    assert_eq!(encoding.ids(), [pad, pad, pad, 97, 98]);
+    assert_eq!(encoding.offsets()[3..], [0..1, 1..2]);
+    // Longer encodings are not cut by padding.
+    assert_eq!(bpe.encode_with_offsets("abcdefg", &vocabulary).len(), 7);
+
+    bpe.set_padding(Padding::new(pad).pad_to_multiple_of(4));
+    let batch = bpe.encode_batch_with_offsets(&["abcde", "a"], &vocabulary);
+    assert_eq!((batch[0].len(), batch[1].len()), (8, 8));
+}
+
+#[test]
+fn model_ready_batch() {
+    let (mut bpe, vocabulary, pad) = setup();
+    bpe.set_truncation(Truncation::new(4).stride(1));
+    bpe.set_padding(Padding::new(pad));
+    let batch = bpe.encode_batch_with_offsets(&["abcdefgh", "xy", ""], &vocabulary);
+
+    for encoding in &batch {
+        assert_eq!(encoding.len(), 4);
+        assert_eq!(encoding.attention_mask().len(), 4);
+    }
+    assert_eq!(text(&batch[0]), "abcd");
+    assert_eq!(batch[0].attention_mask(), [1, 1, 1, 1]);
+    assert_eq!(batch[1].ids(), [120, 121, pad, pad]);
+    assert_eq!(batch[1].attention_mask(), [1, 1, 0, 0]);
+    assert_eq!(batch[2].attention_mask(), [0, 0, 0, 0]);
+    // Overflowing windows are padded like the encoding they belong to.
+    let window = &batch[0].overflowing()[1];
+    assert_eq!(window.ids(), [103, 104, pad, pad]);
+    assert_eq!(window.attention_mask(), [1, 1, 0, 0]);
+}
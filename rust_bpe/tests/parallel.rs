@@ -0,0 +1,54 @@
+#![cfg(feature = "parallel")]
+
+use rust_bpe::{BpeTrainer, RegexPreTokenizer, Vocabulary, BPE};
+
+fn corpus() -> Vec<String> {
+    // Deterministic pseudo-random words, with many ties between pair counts.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..2_000)
+        .map(|_| {
+            (0..8)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    let len = 1 + state % 5;
+                    let word: String = (0..len)
+                        .map(|i| char::from(b'a' + ((state >> (8 * i)) % 6) as u8))
+                        .collect();
+                    format!(" {word}")
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bpe() -> BPE {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().vocab_size(400).min_frequency(1));
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    bpe
+}
+
+fn build_with_threads(threads: usize, documents: &[String]) -> Vocabulary {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| bpe().build_from_iter(documents))
+}
+
+#[test]
+fn identical_for_any_thread_count() {
+    let documents = corpus();
+    let single = build_with_threads(1, &documents);
+    assert_eq!(single.len(), 400);
+    for threads in [2, 3, 8] {
+        assert_eq!(
+            build_with_threads(threads, &documents),
+            single,
+            "{threads} threads"
+        );
+    }
+    // The whole corpus as one document gives the same pieces, and so the same vocabulary.
+    assert_eq!(bpe().build(&documents.concat()), single);
+}
@@ -0,0 +1,192 @@
+use rust_bpe::{BpeTrainer, Error, Padding, PostProcessor, Truncation, Vocabulary, BPE};
+
+const CORPUS: &str = "hello world hello world";
+
+/// Vocabulary with `<s>` = 262, `</s>` = 263 and `<pad>` = 264 after the learned merges.
+fn setup(template: &str) -> (BPE, Vocabulary) {
+    let trainer = BpeTrainer::new()
+        .max_merges(6)
+        .special_tokens(["<s>", "</s>", "<pad>"]);
+    let bpe = BPE::with_trainer(trainer);
+    let mut vocabulary = bpe.build(CORPUS);
+    assert_eq!(vocabulary.special_token_id("<s>"), Some(262));
+    vocabulary
+        .set_post_processor(PostProcessor::new(template).unwrap())
+        .unwrap();
+    (bpe, vocabulary)
+}
+
+#[test]
+fn invalid_templates() {
+    for template in ["<s> </s>", "$B $A", "$A $A", "<s> $A $C", ""] {
+        assert!(
+            matches!(PostProcessor::new(template), Err(Error::InvalidTemplate(_))),
+            "{template:?}"
+        );
+    }
+    let mut vocabulary = BPE::new().build(CORPUS);
+    let processor = PostProcessor::new("[CLS] $A [SEP]").unwrap();
+    assert!(matches!(
+        vocabulary.set_post_processor(processor),
+        Err(Error::InvalidTemplate(_))
+    ));
+    assert!(vocabulary.post_processor().is_none());
+}
+
+#[test]
+fn single_and_pair() {
+    let (bpe, vocabulary) = setup("<s> $A </s> $B </s>");
+    let mut plain = BPE::new();
+    plain.set_add_special_tokens(false);
+    let hello = plain.encode("hello", &vocabulary);
+    let world = plain.encode("world", &vocabulary);
+    assert_eq!(hello, [258, 260]);
+
+    assert_eq!(
+        bpe.encode("hello", &vocabulary),
+        [vec![262], hello.clone(), vec![263]].concat()
+    );
+    let encoding = bpe.encode_with_offsets("hello", &vocabulary);
+    assert_eq!(encoding.ids(), bpe.encode("hello", &vocabulary));
+    assert_eq!(encoding.tokens().first().unwrap(), "<s>");
+    assert_eq!(encoding.offsets().first(), Some(&(0..0)));
+    assert_eq!(encoding.word_ids().last(), Some(&None));
+
+    let pair = bpe.encode_pair_with_offsets("hello", "world", &vocabulary);
+    assert_eq!(
+        pair.ids(),
+        [vec![262], hello, vec![263], world, vec![263]].concat()
+    );
+    // Type ids are 0 unless the template says otherwise.
+    assert!(pair.type_ids().iter().all(|&type_id| type_id == 0));
+    // Offsets of the second input refer to the second input.
+    assert_eq!(pair.offsets()[pair.len() - 2].end, 5);
+}
+
+#[test]
+fn type_ids_and_default_pair() {
+    let (bpe, vocabulary) = setup("<s>:2 $A:0 </s>:3");
+    let pair = bpe.encode_pair_with_offsets("hello", "world", &vocabulary);
+    let ids = pair.ids();
+    assert_eq!((ids[0], pair.type_ids()[0]), (262, 2));
+    // Without `$B` the second input follows the template with type id 1.
+    let end = ids.iter().position(|&id| id == 263).unwrap();
+    assert_eq!(pair.type_ids()[end], 3);
+    assert!(pair.type_ids()[end + 1..]
+        .iter()
+        .all(|&type_id| type_id == 1));
+    assert_eq!(
+        PostProcessor::new("<s>:2 $A:0 </s>:3")
+            .unwrap()
+            .added_tokens(true),
+        2
+    );
+
+    // Without a post-processor, the second input has type id 1 as well.
+    let mut plain = vocabulary.clone();
+    plain.remove_post_processor();
+    let pair = bpe.encode_pair_with_offsets("hi", "yo", &plain);
+    assert_eq!(pair.type_ids(), [0, 0, 1, 1]);
+}
+
+#[test]
+fn truncation_leaves_room_for_added_tokens() {
+    let (mut bpe, vocabulary) = setup("<s> $A </s> $B </s>");
+    bpe.set_truncation(Truncation::new(6));
+    bpe.set_padding(Padding::new(264));
+
+    let encoding = bpe.encode_with_offsets("abcdefghi", &vocabulary);
+    assert_eq!(encoding.tokens(), ["<s>", "a", "b", "c", "d", "</s>"]);
+    // Overflowing windows are wrapped as well, and padded to the same length.
+    let window = &encoding.overflowing()[1];
+    assert_eq!(
+        window.tokens(),
+        ["<s>", "i", "</s>", "<pad>", "<pad>", "<pad>"]
+    );
+    assert_eq!(window.attention_mask(), [1, 1, 1, 0, 0, 0]);
+
+    let pair = bpe.encode_pair_with_offsets("abcdef", "xyz", &vocabulary);
+    assert_eq!(pair.len(), 6);
+    assert_eq!(pair.tokens().concat(), "<s>ab</s>x</s>");
+}
+
+#[test]
+fn decode_skips_special_tokens() {
+    let (mut bpe, vocabulary) = setup("<s> $A </s>");
+    let tokens = bpe.encode("hello world", &vocabulary);
+    assert_eq!(
+        bpe.decode_to_string(&tokens, &vocabulary).unwrap(),
+        "<s>hello world</s>"
+    );
+    bpe.set_skip_special_tokens(true);
+    assert!(bpe.skip_special_tokens());
+    assert_eq!(
+        bpe.decode_to_string(&tokens, &vocabulary).unwrap(),
+        "hello world"
+    );
+    let batch = bpe.encode_batch(&["hello", "world"], &vocabulary);
+    let decoded = bpe.decode_batch(&batch, &vocabulary).unwrap();
+    assert_eq!(decoded, [b"hello".to_vec(), b"world".to_vec()]);
+}
+
+#[test]
+fn saved_with_the_vocabulary() {
+    let (bpe, vocabulary) = setup("<s> $A </s> $B:1 </s>:1");
+    let reloaded = Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded, vocabulary);
+    assert_eq!(
+        reloaded.post_processor().unwrap().template(),
+        "<s> $A </s> $B:1 </s>:1"
+    );
+
+    let hf = vocabulary.to_hf_tokenizer_json();
+    assert_eq!(hf["post_processor"]["type"], "TemplateProcessing");
+    assert_eq!(
+        hf["post_processor"]["special_tokens"]["</s>"]["ids"][0],
+        263
+    );
+    let imported = Vocabulary::from_hf_tokenizer_json(&hf).unwrap();
+    assert_eq!(
+        bpe.encode_pair_with_offsets("hello", "world", &imported),
+        bpe.encode_pair_with_offsets("hello", "world", &vocabulary)
+    );
+}
+
+#[test]
+fn separate_pair_template() {
+    // The post-processor of Llama's `tokenizer.json`, whose single template is not the start of
+    // the pair template.
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/llama-template/post_processor.json"
+    );
+    let post_processor: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+    let (bpe, vocabulary) = setup("$A");
+    let mut hf = vocabulary.to_hf_tokenizer_json();
+    hf["post_processor"] = post_processor;
+
+    let imported = Vocabulary::from_hf_tokenizer_json(&hf).unwrap();
+    let processor = imported.post_processor().unwrap();
+    assert_eq!(processor.template(), "<s>:0 $A:0");
+    assert_eq!(processor.pair_template(), Some("<s>:0 $A:0 <s>:1 $B:1"));
+    assert_eq!(bpe.encode("he", &imported), [262, 104, 101]);
+    let pair = bpe.encode_pair_with_offsets("he", "xy", &imported);
+    assert_eq!(pair.ids(), [262, 104, 101, 262, 120, 121]);
+    assert_eq!(pair.type_ids(), [0, 0, 0, 1, 1, 1]);
+
+    let reloaded = Vocabulary::from_json(&imported.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded, imported);
+    let exported = imported.to_hf_tokenizer_json();
+    assert_eq!(
+        Vocabulary::from_hf_tokenizer_json(&exported).unwrap(),
+        imported
+    );
+
+    for (single, pair) in [("$A $B", "$A $B"), ("<s> $A", "<s> $A"), ("<s>", "$A $B")] {
+        assert!(matches!(
+            PostProcessor::with_pair(single, pair),
+            Err(Error::InvalidTemplate(_))
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use rust_bpe::{BpeTrainer, Error, PreTokenizer, RegexPreTokenizer, Vocabulary, Whitespace, BPE};
+
+const TEXT: &str = "I'LL pay 12345 dollars!\n\n  Don't  stop";
+
+/// Whether `token` mixes whitespace with other bytes.
+fn crosses_whitespace(token: &[u8]) -> bool {
+    token.iter().any(u8::is_ascii_whitespace) && !token.iter().all(u8::is_ascii_whitespace)
+}
+
+fn pieces<'a>(pre_tokenizer: &impl PreTokenizer, text: &'a str) -> Vec<&'a str> {
+    pre_tokenizer
+        .pre_tokenize(text)
+        .into_iter()
+        .map(|range| &text[range])
+        .collect()
+}
+
+#[test]
+fn built_in_patterns() {
+    // Expected pieces come from Python's `regex` module with the same patterns.
+    assert_eq!(
+        pieces(&RegexPreTokenizer::gpt2(), TEXT),
+        ["I", "'", "LL", " pay", " 12345", " dollars", "!", "\n\n ", " Don", "'t", " ", " stop"]
+    );
+    assert_eq!(
+        pieces(&RegexPreTokenizer::cl100k(), TEXT),
+        [
+            "I", "'LL", " pay", " ", "123", "45", " dollars", "!\n\n", " ", " Don", "'t", " ",
+            " stop"
+        ]
+    );
+    assert_eq!(
+        pieces(&Whitespace, TEXT),
+        ["I'LL", " ", "pay", " ", "12345", " ", "dollars!", "\n\n  ", "Don't", "  ", "stop"]
+    );
+    assert_eq!(pieces(&Whitespace, ""), Vec::<&str>::new());
+}
+
+#[test]
+fn custom_pattern() {
+    let digits = RegexPreTokenizer::new(r"\d").unwrap();
+    assert_eq!(digits.pattern(), r"\d");
+    assert_eq!(pieces(&digits, "a12b"), ["1", "2"]);
+    assert!(matches!(
+        RegexPreTokenizer::new("(unclosed"),
+        Err(Error::InvalidPattern(_))
+    ));
+}
+
+#[test]
+fn merges_stay_within_pieces() {
+    let data = "low lower lowest, low low. lower lowest";
+    let trainer = BpeTrainer::new().min_frequency(1);
+
+    let plain = BPE::with_trainer(trainer.clone()).build(data);
+    assert!(plain.pre_tokenizer().is_none());
+    assert!((0..plain.len() as u32).any(|id| crosses_whitespace(plain.token(id).unwrap())));
+
+    let mut bpe = BPE::with_trainer(trainer);
+    bpe.set_pre_tokenizer(Whitespace);
+    let vocabulary = bpe.build(data);
+    assert!(
+        !(0..vocabulary.len() as u32).any(|id| crosses_whitespace(vocabulary.token(id).unwrap()))
+    );
+
+    // Encoding splits the input with the recorded pre-tokenizer and stays lossless on
+    // invalid UTF-8.
+    assert!(vocabulary.pre_tokenizer().is_some());
+    let input = b"lowest \xff lower";
+    let tokens = bpe.encode(input, &vocabulary);
+    assert_eq!(bpe.decode(&tokens, &vocabulary).unwrap(), input);
+    assert_eq!(
+        tokens,
+        [
+            bpe.encode("lowest", &vocabulary),
+            bpe.encode(" ", &vocabulary),
+            vec![255],
+            bpe.encode(" ", &vocabulary),
+            bpe.encode("lower", &vocabulary),
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn persisted_with_the_vocabulary() {
+    let mut bpe = BPE::new();
+    bpe.set_pre_tokenizer(RegexPreTokenizer::cl100k());
+    let vocabulary = bpe.build("the theme of the thesis, the theory");
+
+    let json = vocabulary.to_json().unwrap();
+    assert_eq!(json["pre_tokenizer"]["type"], "Regex");
+    let reloaded = Vocabulary::from_json(&json).unwrap();
+    assert_eq!(reloaded, vocabulary);
+    assert_eq!(
+        bpe.encode(" the theory", &reloaded),
+        bpe.encode(" the theory", &vocabulary)
+    );
+
+    let hf = Vocabulary::from_hf_tokenizer_json(&vocabulary.to_hf_tokenizer_json()).unwrap();
+    assert_eq!(hf, vocabulary);
+}
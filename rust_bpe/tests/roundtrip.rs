@@ -0,0 +1,80 @@
+use rust_bpe::{Error, BPE};
+
+const CORPUS: &str = "the quick brown fox jumps over the lazy dog. \
+                      the quick brown fox jumps over the lazy dog again and again. \
+                      überall grüßen die Füchse, 狐狸跳过懒狗 🦊🦊🦊";
+
+/// Small xorshift generator so the inputs are reproducible without extra dependencies.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, len: usize, alphabet: &[u8]) -> Vec<u8> {
+        (0..len)
+            .map(|_| alphabet[(self.next() % alphabet.len() as u64) as usize])
+            .collect()
+    }
+}
+
+#[test]
+fn roundtrip_training_corpus() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build(CORPUS);
+    let tokens = bpe.encode(CORPUS, &vocabulary);
+    assert!(tokens.len() < CORPUS.len());
+    assert_eq!(bpe.decode_to_string(&tokens, &vocabulary).unwrap(), CORPUS);
+}
+
+#[test]
+fn roundtrip_arbitrary_bytes() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build(CORPUS);
+    let all_bytes: Vec<u8> = (0..=255).collect();
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+
+    let mut inputs = vec![Vec::new(), all_bytes.clone(), CORPUS.as_bytes().to_vec()];
+    for len in 0..200 {
+        inputs.push(rng.bytes(len, &all_bytes));
+        // Inputs drawn from the corpus alphabet exercise the learned merges.
+        inputs.push(rng.bytes(len, b"the quick brown fox"));
+    }
+
+    for input in inputs {
+        let tokens = bpe.encode(&input, &vocabulary);
+        assert_eq!(bpe.decode(&tokens, &vocabulary).unwrap(), input);
+    }
+}
+
+#[test]
+fn encode_is_deterministic() {
+    let bpe = BPE::new();
+    let first = bpe.build(CORPUS);
+    let second = bpe.build(CORPUS);
+    assert_eq!(first, second);
+    assert_eq!(bpe.encode(CORPUS, &first), bpe.encode(CORPUS, &second));
+}
+
+#[test]
+fn decode_errors() {
+    let bpe = BPE::new();
+    let vocabulary = bpe.build(CORPUS);
+
+    let unknown = vocabulary.len() as u32;
+    assert!(matches!(
+        bpe.decode(&[104, unknown], &vocabulary),
+        Err(Error::UnknownToken(id)) if id == unknown
+    ));
+
+    let tokens = bpe.encode(b"ok \xc3", &vocabulary);
+    assert_eq!(bpe.decode(&tokens, &vocabulary).unwrap(), b"ok \xc3");
+    assert!(matches!(
+        bpe.decode_to_string(&tokens, &vocabulary),
+        Err(Error::InvalidUtf8(_))
+    ));
+}
@@ -0,0 +1,89 @@
+use std::ops::Range;
+
+use rust_bpe::{BpeTrainer, Error, PreTokenizer, Vocabulary, BPE};
+
+const CORPUS: &str = "low lower lowest newer newest wider widest \u{0}\u{7f} ünïcödé ünïcödé";
+
+fn trained() -> Vocabulary {
+    let trainer = BpeTrainer::new().special_tokens(["<|endoftext|>", "<pad>"]);
+    BPE::with_trainer(trainer).build(CORPUS)
+}
+
+#[test]
+fn save_and_load() {
+    let vocabulary = trained();
+    let path = std::env::temp_dir().join(format!("rust_bpe_vocab_{}.json", std::process::id()));
+    vocabulary.save(&path).unwrap();
+    let loaded = Vocabulary::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded, vocabulary);
+    let bpe = BPE::new();
+    assert_eq!(bpe.encode(CORPUS, &loaded), bpe.encode(CORPUS, &vocabulary));
+}
+
+#[test]
+fn schema() {
+    let json = trained().to_json().unwrap();
+    assert_eq!(json["version"], rust_bpe::FORMAT_VERSION);
+    assert_eq!(json["vocab"]["Ġ"], 32);
+    assert_eq!(json["vocab"]["lo"], 257);
+    assert_eq!(json["merges"][1], serde_json::json!([108, 111, 257]));
+    assert!(json["special_tokens"]["<|endoftext|>"].is_u64());
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let mut json = trained().to_json().unwrap();
+    json["version"] = 99.into();
+    assert!(matches!(
+        Vocabulary::from_json(&json),
+        Err(Error::UnsupportedVersion(99))
+    ));
+
+    let mut json = trained().to_json().unwrap();
+    json["merges"][0] = serde_json::json!([97, 98, 99]);
+    assert!(matches!(
+        Vocabulary::from_json(&json),
+        Err(Error::InvalidVocabulary(_))
+    ));
+
+    assert!(matches!(
+        Vocabulary::load("does/not/exist.json"),
+        Err(Error::Io(_))
+    ));
+}
+
+/// Splits text into pairs of bytes, without a description to save.
+#[derive(Debug)]
+struct Pairs;
+
+impl PreTokenizer for Pairs {
+    fn pre_tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        (0..text.len())
+            .step_by(2)
+            .map(|start| start..(start + 2).min(text.len()))
+            .collect()
+    }
+}
+
+#[test]
+fn rejects_pre_tokenizers_without_description() {
+    let mut vocabulary = BPE::new().build("abababcab");
+    vocabulary.set_pre_tokenizer(Pairs);
+    assert!(matches!(
+        vocabulary.to_json(),
+        Err(Error::InvalidVocabulary(_))
+    ));
+    let path = std::env::temp_dir().join(format!("rust_bpe_pairs_{}.json", std::process::id()));
+    assert!(matches!(
+        vocabulary.save(&path),
+        Err(Error::InvalidVocabulary(_))
+    ));
+    assert!(!path.exists());
+
+    vocabulary.remove_pre_tokenizer();
+    let reloaded = Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded, vocabulary);
+}
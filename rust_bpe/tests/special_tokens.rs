@@ -0,0 +1,128 @@
+use rust_bpe::{AllowedSpecial, BpeTrainer, Normalizer, RegexPreTokenizer, Vocabulary, BPE};
+
+const CORPUS: &str = "<|endoftext|>hello world<|endoftext|>hello<|endoftext|> world<|endoftext|>";
+
+fn bpe() -> BPE {
+    let trainer =
+        BpeTrainer::new()
+            .min_frequency(1)
+            .special_tokens(["<|endoftext|>", "<s>", "<s>>"]);
+    let mut bpe = BPE::with_trainer(trainer);
+    bpe.set_allowed_special(AllowedSpecial::All);
+    bpe
+}
+
+#[test]
+fn special_tokens_are_not_learned_from() {
+    let vocabulary = bpe().build(CORPUS);
+    for id in 256..vocabulary.len() as u32 {
+        if !vocabulary.special_tokens().contains(&id) {
+            let token = vocabulary.token(id).unwrap();
+            assert!(
+                !token.contains(&b'<') && !token.contains(&b'|'),
+                "{token:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn matched_atomically() {
+    let bpe = bpe();
+    let vocabulary = bpe.build(CORPUS);
+    let end_of_text = vocabulary.special_token_id("<|endoftext|>").unwrap();
+    let start = vocabulary.special_token_id("<s>").unwrap();
+    let start_longer = vocabulary.special_token_id("<s>>").unwrap();
+
+    assert_eq!(
+        bpe.encode("hello<|endoftext|>world", &vocabulary),
+        [
+            bpe.encode("hello", &vocabulary),
+            vec![end_of_text],
+            bpe.encode("world", &vocabulary),
+        ]
+        .concat()
+    );
+    // The longest special token wins, overlapping occurrences are matched left to right.
+    assert_eq!(bpe.encode("<s>><s>", &vocabulary), [start_longer, start]);
+    assert_eq!(
+        bpe.encode("<<s>", &vocabulary),
+        [vocabulary.byte_id(b'<'), start]
+    );
+    let tokens = bpe.encode("x<|endoftext|><|endoftext|>", &vocabulary);
+    assert_eq!(tokens[1..], [end_of_text, end_of_text]);
+    assert_eq!(
+        bpe.decode_to_string(&tokens, &vocabulary).unwrap(),
+        "x<|endoftext|><|endoftext|>"
+    );
+}
+
+#[test]
+fn allow_and_deny() {
+    let mut bpe = bpe();
+    let vocabulary = bpe.build(CORPUS);
+    let end_of_text = vocabulary.special_token_id("<|endoftext|>").unwrap();
+    let start = vocabulary.special_token_id("<s>").unwrap();
+    let input = "<s>hello<|endoftext|>";
+    // Special tokens are plain text unless they are allowed.
+    let literal = BPE::new();
+    assert_eq!(literal.allowed_special(), &AllowedSpecial::None);
+    let plain = |text: &str| literal.encode(text, &vocabulary);
+
+    bpe.set_allowed_special(AllowedSpecial::None);
+    let tokens = bpe.encode(input, &vocabulary);
+    assert!(!tokens
+        .iter()
+        .any(|id| vocabulary.special_tokens().contains(id)));
+    assert_eq!(bpe.decode_to_string(&tokens, &vocabulary).unwrap(), input);
+
+    bpe.set_allowed_special(AllowedSpecial::Only(vec!["<s>".into()]));
+    assert_eq!(
+        bpe.encode(input, &vocabulary),
+        [vec![start], plain("hello<|endoftext|>")].concat()
+    );
+
+    bpe.set_allowed_special(AllowedSpecial::Except(vec!["<s>".into()]));
+    assert_eq!(
+        bpe.encode(input, &vocabulary),
+        [plain("<s>hello"), vec![end_of_text]].concat()
+    );
+}
+
+#[test]
+fn added_tokens_keep_their_ids() {
+    let mut bpe = BPE::new();
+    bpe.set_allowed_special(AllowedSpecial::All);
+    let mut vocabulary = bpe.build("hello world hello world");
+    let len = vocabulary.len() as u32;
+    let pad = vocabulary.add_special_token("<pad>");
+    let unk = vocabulary.add_special_token("<unk>");
+    assert_eq!((pad, unk), (len, len + 1));
+    assert_eq!(vocabulary.add_special_token("<pad>"), pad);
+    assert_eq!(vocabulary.special_token_id("<unk>"), Some(unk));
+    assert_eq!(vocabulary.special_token_id("hello"), None);
+
+    let reloaded = Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded.special_token_id("<pad>"), Some(pad));
+    assert_eq!(bpe.encode("<unk><pad>", &reloaded), [unk, pad]);
+}
+
+#[test]
+fn matched_before_normalization_and_pre_tokenization() {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<PAD>"]));
+    bpe.set_normalizer(Normalizer::Lowercase);
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    bpe.set_allowed_special(AllowedSpecial::All);
+    let vocabulary = bpe.build("Hello Hello");
+    let pad = vocabulary.special_token_id("<PAD>").unwrap();
+
+    assert_eq!(
+        bpe.encode("HELLO<PAD><pad>", &vocabulary),
+        [
+            bpe.encode("hello", &vocabulary),
+            vec![pad],
+            bpe.encode("<pad>", &vocabulary),
+        ]
+        .concat()
+    );
+}
@@ -0,0 +1,273 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use rust_bpe::{
+    AllowedSpecial, BpeTrainer, Error, Normalizer, RegexPreTokenizer, StreamDecoder, StreamEncoder,
+    Vocabulary, Whitespace, BPE,
+};
+
+const CORPUS: &str = "the quick brown fox\njumps over the lazy dog\nthe dog sleeps\nthe fox runs\n";
+
+fn bpe() -> BPE {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<|endoftext|>"]));
+    bpe.set_pre_tokenizer(Whitespace);
+    bpe
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rust_bpe_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn sources_agree() {
+    // With single line breaks, splitting by line gives the same pieces as splitting the whole.
+    let bpe = bpe();
+    let expected = bpe.build(CORPUS);
+    assert!(expected.merges().len() > 5);
+
+    assert_eq!(bpe.build_from_iter(CORPUS.split_inclusive('\n')), expected);
+    assert_eq!(bpe.build_from_reader(CORPUS.as_bytes()).unwrap(), expected);
+
+    let (first, second) = CORPUS.split_at(CORPUS.find("the dog").unwrap());
+    let paths = [temp_path("first.txt"), temp_path("second.txt")];
+    std::fs::write(&paths[0], first).unwrap();
+    std::fs::write(&paths[1], second).unwrap();
+    let from_files = bpe.build_from_files(&paths);
+    for path in &paths {
+        std::fs::remove_file(path).unwrap();
+    }
+    assert_eq!(from_files.unwrap(), expected);
+}
+
+#[test]
+fn documents_are_split_independently() {
+    let bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1));
+    let vocabulary = bpe.build_from_iter(["ab", "cd"]);
+    assert_eq!(vocabulary.merges().len(), 2);
+    assert_eq!(bpe.build("abcd").merges().len(), 3);
+
+    // Byte documents may hold invalid UTF-8.
+    let vocabulary = bpe.build_from_iter([&b"\xff\xfe"[..], b"\xff\xfe"]);
+    assert_eq!(vocabulary.token_id(b"\xff\xfe"), Some(256));
+}
+
+#[test]
+fn long_lines_are_read_in_chunks() {
+    let bpe = bpe();
+    let text = "word ".repeat(300_000) + "<|endoftext|>end";
+    assert_eq!(
+        bpe.build_from_reader(text.as_bytes()).unwrap(),
+        bpe.build(&text)
+    );
+}
+
+#[test]
+fn readers_split_like_build() {
+    // Splitting by line would cut the `" \n"` piece of the GPT-2 pattern, and the runs of
+    // letters and spaces are longer than a chunk.
+    let text = "ab \ncd ab \ncd ab \ncd<|endoftext|>ab ".repeat(3)
+        + &"x".repeat(70_000)
+        + &" ".repeat(70_000)
+        + "yz yz cafe\u{301} caf\u{e9}\n\n";
+    // Read a few bytes at a time, with characters cut across reads and invalid UTF-8.
+    let mut data = "ab \ncd caf\u{e9} ab \ncd<|endoftext|>"
+        .repeat(20)
+        .into_bytes();
+    data.extend_from_slice(b"\xff\xe2\x82 ab\xe2\x82");
+
+    for (pre_tokenizer, normalizer) in [
+        (Some(RegexPreTokenizer::gpt2()), None),
+        (Some(RegexPreTokenizer::cl100k()), None),
+        (None, None),
+        (Some(RegexPreTokenizer::gpt2()), Some(Normalizer::Nfc)),
+    ] {
+        let trainer = BpeTrainer::new()
+            .min_frequency(1)
+            .special_tokens(["<|endoftext|>"]);
+        let mut bpe = BPE::with_trainer(trainer);
+        if let Some(pre_tokenizer) = pre_tokenizer {
+            bpe.set_pre_tokenizer(pre_tokenizer);
+        }
+        if let Some(normalizer) = normalizer {
+            bpe.set_normalizer(normalizer);
+        }
+        assert_eq!(
+            bpe.build_from_reader(text.as_bytes()).unwrap(),
+            bpe.build(&text)
+        );
+        let reader = Trickle {
+            data: &data,
+            reads: 0,
+        };
+        assert_eq!(
+            bpe.build_from_reader(reader).unwrap(),
+            bpe.build_from_iter([&data])
+        );
+    }
+}
+
+#[test]
+fn missing_file() {
+    assert!(matches!(
+        BPE::new().build_from_files(["does/not/exist.txt"]),
+        Err(Error::Io(_))
+    ));
+}
+
+/// Hands out its data a few bytes at a time, so pieces and characters fall across reads.
+struct Trickle<'a> {
+    data: &'a [u8],
+    reads: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        let len = (self.reads % 4 + 1).min(self.data.len()).min(buf.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+fn stream_encode(bpe: &BPE, vocabulary: &Vocabulary, data: &[u8]) -> Vec<u32> {
+    let reader = Trickle { data, reads: 0 };
+    StreamEncoder::new(bpe, vocabulary, reader)
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn stream_encoder_matches_encode() {
+    let input = "The  quick\r\n\r\nbrown fox's 12345 jumps\t \n<|endoftext|><|endof \
+        ΟΔΟΣ. Cafe\u{301} caf\u{e9}  ﬁne²<|endoftext|>\n\nend  ";
+    let mut data = input.as_bytes().to_vec();
+    data.extend_from_slice(b"\xff\xe2\x82 tail\xe2\x82");
+
+    let mut configurations = Vec::new();
+    let mut bpe = bpe();
+    configurations.push(BPE::with_trainer(
+        BpeTrainer::new().special_tokens(["<|endoftext|>"]),
+    ));
+    bpe.set_normalizer(Normalizer::Sequence(vec![
+        Normalizer::Nfkc,
+        Normalizer::Lowercase,
+    ]));
+    configurations.push(bpe);
+    for pre_tokenizer in [RegexPreTokenizer::gpt2(), RegexPreTokenizer::cl100k()] {
+        let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<|endoftext|>"]));
+        bpe.set_pre_tokenizer(pre_tokenizer);
+        configurations.push(bpe);
+    }
+    let mut nfd = BPE::with_trainer(BpeTrainer::new().special_tokens(["<|endoftext|>"]));
+    nfd.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    nfd.set_normalizer(Normalizer::Nfd);
+    configurations.push(nfd);
+
+    for bpe in &mut configurations {
+        bpe.set_allowed_special(AllowedSpecial::All);
+        let mut vocabulary = bpe.build(&input.repeat(3));
+        assert_eq!(
+            stream_encode(bpe, &vocabulary, &data),
+            bpe.encode(&data, &vocabulary)
+        );
+        vocabulary.set_add_prefix_space(true);
+        assert_eq!(
+            stream_encode(bpe, &vocabulary, &data),
+            bpe.encode(&data, &vocabulary)
+        );
+    }
+}
+
+#[test]
+fn stream_encoder_holds_text_for_custom_pre_tokenizers() {
+    // The first `a` only becomes part of a longer piece once the `b` arrives.
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1));
+    bpe.set_pre_tokenizer(RegexPreTokenizer::new("a+b|a").unwrap());
+    let vocabulary = bpe.build("aaaab aaaab");
+    let data = "a".repeat(1_000) + "b a";
+    assert_eq!(
+        stream_encode(&bpe, &vocabulary, data.as_bytes()),
+        bpe.encode(&data, &vocabulary)
+    );
+}
+
+#[test]
+fn stream_encoder_reads_long_normalized_runs() {
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().special_tokens(["<|endoftext|>"]));
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    bpe.set_normalizer(Normalizer::Nfc);
+    bpe.set_allowed_special(AllowedSpecial::All);
+    let vocabulary = bpe.build("cafe\u{301}s, cafe\u{301}s");
+    // Several chunks of input without whitespace, where the text cannot be cut.
+    let data = "cafe\u{301},".repeat(50_000) + "<|endoftext|>cafe\u{301}";
+    let ids: Vec<u32> = StreamEncoder::new(&bpe, &vocabulary, data.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids, bpe.encode(&data, &vocabulary));
+}
+
+#[test]
+fn stream_encoder_yields_ids_before_the_end() {
+    let mut bpe = bpe();
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    let vocabulary = bpe.build(CORPUS);
+    // The reader fails after the text, so ids are only produced if they come early.
+    let reader = CORPUS.as_bytes().chain(FailingReader);
+    let mut encoder = StreamEncoder::new(&bpe, &vocabulary, reader);
+    let first = encoder.next().unwrap().unwrap();
+    assert_eq!(first, bpe.encode("the", &vocabulary)[0]);
+    assert!(matches!(encoder.by_ref().last(), Some(Err(Error::Io(_)))));
+    assert!(encoder.next().is_none());
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("disconnected"))
+    }
+}
+
+/// Records every write separately.
+#[derive(Default)]
+struct Writes(Vec<Vec<u8>>);
+
+impl Write for Writes {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn stream_decoder_completes_characters() {
+    let bpe = BPE::new();
+    let text = "naïve 日本語 🦀";
+    let vocabulary = bpe.build(&text.repeat(3));
+    let ids = bpe.encode(text, &vocabulary);
+
+    let mut decoder = StreamDecoder::new(&bpe, &vocabulary, Writes::default());
+    for &id in &ids {
+        decoder.write(&[id]).unwrap();
+    }
+    let writes = decoder.finish().unwrap().0;
+    assert!(writes
+        .iter()
+        .all(|bytes| std::str::from_utf8(bytes).is_ok()));
+    assert_eq!(writes.concat(), text.as_bytes());
+
+    // Invalid bytes are passed on, an unfinished character is written by `finish`.
+    let mut decoder = StreamDecoder::new(&bpe, &vocabulary, Vec::new());
+    decoder.write(&[0xff, 0x61, 0xe6]).unwrap();
+    decoder.write(&[0x97]).unwrap();
+    assert!(matches!(
+        decoder.write(&[1_000_000]),
+        Err(Error::UnknownToken(_))
+    ));
+    assert_eq!(decoder.finish().unwrap(), b"\xff\x61\xe6\x97");
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rust_bpe::{Error, MergeMode, RegexPreTokenizer, Vocabulary, BPE};
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/gpt2-small")
+        .join(name)
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rust_bpe_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn golden_encodings() {
+    let mut vocabulary = Vocabulary::from_tiktoken_file(fixture("ranks.tiktoken")).unwrap();
+    assert_eq!(vocabulary.merge_mode(), MergeMode::Ranks);
+    // The GPT-2 regex is the pattern of tiktoken's r50k_base encoding.
+    vocabulary.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+
+    let golden: Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture("golden_tiktoken.json")).unwrap())
+            .unwrap();
+    let bpe = BPE::new();
+    for case in golden.as_array().unwrap() {
+        let text = case["text"].as_str().unwrap();
+        let ids: Vec<u32> = serde_json::from_value(case["ids"].clone()).unwrap();
+        assert_eq!(bpe.encode(text, &vocabulary), ids, "{text:?}");
+        assert_eq!(bpe.decode_to_string(&ids, &vocabulary).unwrap(), text);
+    }
+}
+
+#[test]
+fn whole_piece_lookup() {
+    // "abc" is a token, but neither "ab" nor "bc" is, so it can only be found as a whole.
+    let path = temp_path("whole_piece.tiktoken");
+    let mut ranks: String = (0..=255u8)
+        .map(|b| format!("{} {b}\n", STANDARD.encode([b])))
+        .collect();
+    ranks.push_str("YWJj 256\n");
+    std::fs::write(&path, ranks).unwrap();
+    let vocabulary = Vocabulary::from_tiktoken_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut vocabulary = vocabulary.unwrap();
+    assert!(vocabulary.merges().is_empty());
+    let bpe = BPE::new();
+    assert_eq!(bpe.encode("abc", &vocabulary), [256]);
+    assert_eq!(bpe.encode("abcd", &vocabulary), [97, 98, 99, 100]);
+    vocabulary.set_merge_mode(MergeMode::Merges);
+    assert_eq!(bpe.encode("abc", &vocabulary), [97, 98, 99]);
+}
+
+#[test]
+fn export_roundtrip() {
+    let vocabulary = Vocabulary::from_tiktoken_file(fixture("ranks.tiktoken")).unwrap();
+    let path = temp_path("export.tiktoken");
+    vocabulary.save_tiktoken_file(&path).unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        written,
+        std::fs::read_to_string(fixture("ranks.tiktoken")).unwrap()
+    );
+
+    // The derived merges reproduce the merge list the ranks were made from.
+    let gpt2 = Vocabulary::load_hf_tokenizer(fixture("tokenizer.json")).unwrap();
+    assert_eq!(vocabulary.merges(), gpt2.merges());
+    let reloaded = Vocabulary::from_json(&vocabulary.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded, vocabulary);
+}
+
+#[test]
+fn rejects_ranks_out_of_range() {
+    let path = temp_path("huge_rank.tiktoken");
+    std::fs::write(&path, "YQ== 4000000000\n").unwrap();
+    let result = Vocabulary::from_tiktoken_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(Error::InvalidVocabulary(_))));
+}
@@ -0,0 +1,51 @@
+use rust_bpe::{BpeTrainer, BPE};
+
+const CORPUS: &str = "the quick brown fox jumps over the lazy dog. \
+                      the quick brown fox jumps over the lazy dog again and again.";
+
+#[test]
+fn defaults() {
+    assert_eq!(BpeTrainer::default(), BpeTrainer::new());
+    assert_eq!(BPE::new().trainer(), &BpeTrainer::new());
+    // Pairs occurring once are not merged by default.
+    assert_eq!(BPE::new().build("abcd").merges().len(), 0);
+}
+
+#[test]
+fn vocab_sizes_from_one_corpus() {
+    let full = BPE::new().build(CORPUS);
+    assert!(full.len() > 280);
+    for vocab_size in [256, 260, 270, 280] {
+        let vocabulary = BPE::with_trainer(BpeTrainer::new().vocab_size(vocab_size)).build(CORPUS);
+        assert_eq!(vocabulary.len(), vocab_size);
+        // Smaller vocabularies stop early on the same sequence of merges.
+        assert_eq!(vocabulary.merges(), &full.merges()[..vocab_size - 256]);
+    }
+}
+
+#[test]
+fn min_frequency_and_max_merges() {
+    let frequent = BPE::with_trainer(BpeTrainer::new().min_frequency(4)).build(CORPUS);
+    let all = BPE::with_trainer(BpeTrainer::new().min_frequency(1)).build(CORPUS);
+    assert!(frequent.merges().len() < BPE::new().build(CORPUS).merges().len());
+    assert!(all.merges().len() > BPE::new().build(CORPUS).merges().len());
+    assert_eq!(frequent.merges(), &all.merges()[..frequent.merges().len()]);
+
+    let capped = BPE::with_trainer(BpeTrainer::new().min_frequency(1).max_merges(5)).build(CORPUS);
+    assert_eq!(capped.merges(), &all.merges()[..5]);
+}
+
+#[test]
+fn special_tokens_are_reserved() {
+    let trainer = BpeTrainer::new().vocab_size(270).special_tokens([
+        "<|endoftext|>",
+        "<pad>",
+        "<|endoftext|>",
+    ]);
+    let vocabulary = BPE::with_trainer(trainer).build(CORPUS);
+    assert_eq!(vocabulary.len(), 270);
+    assert_eq!(vocabulary.merges().len(), 12);
+    assert_eq!(vocabulary.special_tokens(), [268, 269]);
+    assert_eq!(vocabulary.special_token_id("<|endoftext|>"), Some(268));
+    assert_eq!(vocabulary.token(269), Some(&b"<pad>"[..]));
+}
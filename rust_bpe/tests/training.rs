@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use rust_bpe::{BpeTrainer, Merge, PreTokenizer, RegexPreTokenizer, BPE};
+
+/// The textbook algorithm: recount every pair after each merge, ties going to the smaller pair
+/// of ids, and reuse the id of a token that is spelled by an earlier merge.
+fn reference_merges(documents: &[String], num_merges: usize) -> Vec<Merge> {
+    let mut words: HashMap<Vec<u32>, u64> = HashMap::new();
+    for document in documents {
+        for range in RegexPreTokenizer::gpt2().pre_tokenize(document) {
+            let word = document[range].bytes().map(u32::from).collect();
+            *words.entry(word).or_default() += 1;
+        }
+    }
+    let mut words: Vec<_> = words.into_iter().collect();
+    let mut tokens: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).collect();
+    let mut merges = Vec::new();
+    while merges.len() < num_merges {
+        let mut counts: HashMap<(u32, u32), u64> = HashMap::new();
+        for (word, freq) in &words {
+            for pair in word.windows(2) {
+                *counts.entry((pair[0], pair[1])).or_default() += freq;
+            }
+        }
+        let best = counts
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.cmp(a)));
+        let Some(((left, right), _)) = best else {
+            break;
+        };
+
+        let bytes = [
+            tokens[left as usize].clone(),
+            tokens[right as usize].clone(),
+        ]
+        .concat();
+        let id = match tokens.iter().position(|token| *token == bytes) {
+            Some(id) => id as u32,
+            None => {
+                tokens.push(bytes);
+                tokens.len() as u32 - 1
+            }
+        };
+        for (word, _) in &mut words {
+            let mut merged = Vec::with_capacity(word.len());
+            let mut i = 0;
+            while i < word.len() {
+                if i + 1 < word.len() && word[i] == left && word[i + 1] == right {
+                    merged.push(id);
+                    i += 2;
+                } else {
+                    merged.push(word[i]);
+                    i += 1;
+                }
+            }
+            *word = merged;
+        }
+        merges.push(Merge { left, right, id });
+    }
+    merges
+}
+
+fn corpus() -> Vec<String> {
+    let mut state = 0x853c_49e6_748f_ea9bu64;
+    (0..300)
+        .map(|_| {
+            (0..10)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    let len = 1 + state % 6;
+                    let word: String = (0..len)
+                        .map(|i| char::from(b"aab"[((state >> (4 * i)) % 3) as usize]))
+                        .collect();
+                    format!(" {word}")
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn matches_full_recount() {
+    let documents = corpus();
+    let mut bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1).max_merges(120));
+    bpe.set_pre_tokenizer(RegexPreTokenizer::gpt2());
+    let vocabulary = bpe.build_from_iter(&documents);
+
+    let expected = reference_merges(&documents, 120);
+    assert!(expected.len() > 50);
+    assert_eq!(vocabulary.merges(), expected);
+}
+
+#[test]
+fn overlapping_pairs() {
+    // Runs of one byte overlap with themselves, only every other pair can be merged.
+    let bpe = BPE::with_trainer(BpeTrainer::new().min_frequency(1));
+    let vocabulary = bpe.build("aaaaaaa");
+    let tokens: Vec<&[u8]> = vocabulary
+        .merges()
+        .iter()
+        .map(|merge| vocabulary.token(merge.id).unwrap())
+        .collect();
+    assert_eq!(tokens, [&b"aa"[..], b"aaaa", b"aaa", b"aaaaaaa"]);
+}